@@ -0,0 +1,805 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::config::{AlertsConfig, Config, DisplayConfig, WebhookConfig, WebhookFormat};
+use crate::http::HttpClient;
+use crate::providers::QuotaInfo;
+use crate::storage::{Account, SecureStorage};
+
+/// Something worth telling the user about, sent once per crossing
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Alert {
+    Threshold {
+        account: String,
+        provider: String,
+        /// `requests`, `tokens` or `cost`
+        metric: &'static str,
+        threshold: f64,
+        percent: f64,
+        used: f64,
+        limit: f64,
+        reset_date: Option<chrono::DateTime<chrono::Utc>>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    Reset {
+        account: String,
+        provider: String,
+        previous_reset: chrono::DateTime<chrono::Utc>,
+        reset_date: chrono::DateTime<chrono::Utc>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    FetchFailed {
+        account: String,
+        provider: String,
+        error: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+/// Alert types webhooks can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    Threshold,
+    Reset,
+    FetchFailed,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 3] = [
+        AlertKind::Threshold,
+        AlertKind::Reset,
+        AlertKind::FetchFailed,
+    ];
+}
+
+impl Alert {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            Alert::Threshold { .. } => AlertKind::Threshold,
+            Alert::Reset { .. } => AlertKind::Reset,
+            Alert::FetchFailed { .. } => AlertKind::FetchFailed,
+        }
+    }
+
+    fn account(&self) -> &str {
+        match self {
+            Alert::Threshold { account, .. }
+            | Alert::Reset { account, .. }
+            | Alert::FetchFailed { account, .. } => account,
+        }
+    }
+
+    /// Whether the alert needs attention rather than being informational, i.e. usage has
+    /// reached `display.critical_threshold`
+    fn is_urgent(&self, display: &DisplayConfig) -> bool {
+        match self {
+            Alert::Threshold { percent, .. } => {
+                display.usage_level(*percent) == crate::config::UsageLevel::Critical
+            }
+            Alert::Reset { .. } => false,
+            Alert::FetchFailed { .. } => true,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Alert::Threshold {
+                account,
+                metric,
+                threshold,
+                ..
+            } => format!("{}: {} above {}%", account, metric, threshold),
+            Alert::Reset { account, .. } => format!("{}: quota reset", account),
+            Alert::FetchFailed { account, .. } => format!("{}: quota fetch failing", account),
+        }
+    }
+
+    /// One-line message such as "shared-openrouter at 85% of its cost limit ($170.00/$200.00)"
+    pub fn summary(&self, display: &DisplayConfig) -> String {
+        match self {
+            Alert::Threshold {
+                account,
+                metric,
+                percent,
+                used,
+                limit,
+                ..
+            } => {
+                let value = |v: f64| match *metric {
+                    "cost" => format!("${:.2}", v),
+                    _ => format!("{:.0}", v),
+                };
+                format!(
+                    "{} at {:.0}% of its {} limit ({}/{})",
+                    account,
+                    percent,
+                    metric,
+                    value(*used),
+                    value(*limit)
+                )
+            }
+            Alert::Reset {
+                account,
+                reset_date,
+                ..
+            } => format!(
+                "{} quota has reset, next reset {}",
+                account,
+                display.format_datetime(*reset_date)
+            ),
+            Alert::FetchFailed { account, error, .. } => {
+                format!("{}: fetching quota failed: {}", account, error)
+            }
+        }
+    }
+
+    /// Alert fields plus the summary, as sent to commands and generic JSON webhooks
+    pub fn payload(&self, display: &DisplayConfig) -> serde_json::Value {
+        let mut payload = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = payload.as_object_mut() {
+            fields.insert("summary".to_string(), self.summary(display).into());
+        }
+        payload
+    }
+}
+
+/// What has already been announced for one account
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccountAlertState {
+    /// Highest threshold crossed per metric; lowered again when usage drops, e.g. after a reset
+    #[serde(default)]
+    crossed: HashMap<String, f64>,
+    #[serde(default)]
+    failing: bool,
+    /// Last reset date reported, used to notice when the quota resets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reset_date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Where an alert is delivered
+#[derive(Debug, Clone)]
+pub enum Target {
+    Desktop,
+    Command(String),
+    Webhook(WebhookConfig),
+}
+
+impl Target {
+    pub fn describe(&self) -> String {
+        match self {
+            Target::Desktop => "desktop notification".to_string(),
+            Target::Command(command) => format!("command '{}'", command),
+            Target::Webhook(webhook) => {
+                format!("{} webhook {}", webhook.format.as_str(), webhook.url)
+            }
+        }
+    }
+
+    /// Webhooks can be limited to some alert types and accounts
+    fn accepts(&self, alert: &Alert) -> bool {
+        match self {
+            Target::Webhook(webhook) => {
+                webhook.events.contains(&alert.kind())
+                    && (webhook.accounts.is_empty()
+                        || webhook.accounts.iter().any(|name| name == alert.account()))
+            }
+            _ => true,
+        }
+    }
+
+    /// Webhooks go through the shared HTTP client, so `[http]` proxy and CA settings apply
+    pub async fn deliver(
+        &self,
+        alert: &Alert,
+        display: &DisplayConfig,
+        client: &HttpClient,
+    ) -> Result<()> {
+        match self {
+            Target::Desktop | Target::Command(_) => {
+                // Both wait for a process, so keep them off the async runtime's threads
+                let (target, alert, display) = (self.clone(), alert.clone(), display.clone());
+                tokio::task::spawn_blocking(move || match &target {
+                    Target::Command(command) => run_alert_command(command, &alert, &display),
+                    _ => send_desktop_notification(&alert, &display),
+                })
+                .await
+                .context("Alert delivery panicked")?
+            }
+            Target::Webhook(webhook) => post_webhook(client, webhook, alert, display).await,
+        }
+    }
+}
+
+type AlertState = HashMap<String, AccountAlertState>;
+
+/// Decides which alerts to send and remembers them across runs in `alert_state.json`.
+///
+/// The dashboard, `serve`, the daemon and one-off commands may all run at once, so the state
+/// is read again, updated and written back under a file lock for every batch of results.
+pub struct Alerter {
+    config: AlertsConfig,
+    display: DisplayConfig,
+    client: HttpClient,
+    state_path: PathBuf,
+}
+
+impl Alerter {
+    pub fn new(storage: &SecureStorage, config: &Config, client: &HttpClient) -> Self {
+        Self {
+            config: config.alerts.clone(),
+            display: config.display.clone(),
+            client: client.clone(),
+            state_path: storage.alert_state_path(),
+        }
+    }
+
+    /// Compare fresh fetch results with what was announced before, by this or any other
+    /// process, and return new alerts
+    pub fn observe<'a>(
+        &self,
+        results: impl IntoIterator<Item = (&'a Account, &'a Result<QuotaInfo>)>,
+    ) -> Vec<Alert> {
+        let _lock = crate::storage::lock_file(&self.state_path)
+            .map_err(|e| tracing::warn!("{:#}", e))
+            .ok();
+
+        let mut state: AlertState = fs::read_to_string(&self.state_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let alerts = results
+            .into_iter()
+            .flat_map(|(account, result)| self.observe_one(&mut state, account, result))
+            .collect();
+
+        if let Err(e) = self.save(&state) {
+            tracing::warn!("{:#}", e);
+        }
+        alerts
+    }
+
+    fn observe_one(
+        &self,
+        state: &mut AlertState,
+        account: &Account,
+        result: &Result<QuotaInfo>,
+    ) -> Vec<Alert> {
+        let now = chrono::Utc::now();
+        let state = state.entry(account.name.clone()).or_default();
+        let mut alerts = Vec::new();
+
+        match result {
+            Ok(quota) => {
+                state.failing = false;
+
+                // The reset date moving on after the previous one has passed means a new period
+                if let (Some(previous_reset), Some(reset_date)) =
+                    (state.reset_date, quota.reset_date)
+                {
+                    if self.config.on_reset && reset_date > previous_reset && previous_reset <= now
+                    {
+                        alerts.push(Alert::Reset {
+                            account: account.name.clone(),
+                            provider: account.provider.clone(),
+                            previous_reset,
+                            reset_date,
+                            timestamp: now,
+                        });
+                    }
+                }
+                if quota.reset_date.is_some() {
+                    state.reset_date = quota.reset_date;
+                }
+
+                for (metric, used, limit) in quota.usage_against_limits() {
+                    let percent = used / limit * 100.0;
+                    let crossed = self
+                        .config
+                        .thresholds
+                        .iter()
+                        .copied()
+                        .filter(|threshold| percent >= *threshold)
+                        .reduce(f64::max);
+                    let previous = state.crossed.get(metric).copied();
+
+                    if let Some(threshold) = crossed {
+                        if previous.is_none_or(|previous| threshold > previous) {
+                            alerts.push(Alert::Threshold {
+                                account: account.name.clone(),
+                                provider: account.provider.clone(),
+                                metric,
+                                threshold,
+                                percent,
+                                used,
+                                limit,
+                                reset_date: quota.reset_date,
+                                timestamp: now,
+                            });
+                        }
+                    }
+
+                    match crossed {
+                        Some(threshold) => state.crossed.insert(metric.to_string(), threshold),
+                        None => state.crossed.remove(metric),
+                    };
+                }
+            }
+            // Only a cache miss with --offline, the account's state is unknown
+            Err(e) if e.is::<crate::providers::NotCached>() => {}
+            Err(e) => {
+                if !state.failing && self.config.on_fetch_failure {
+                    alerts.push(Alert::FetchFailed {
+                        account: account.name.clone(),
+                        provider: account.provider.clone(),
+                        error: format!("{:#}", e),
+                        timestamp: now,
+                    });
+                }
+                state.failing = true;
+            }
+        }
+
+        alerts
+    }
+
+    /// Persist what has been announced so restarts and refreshes don't repeat alerts
+    fn save(&self, state: &AlertState) -> Result<()> {
+        let content =
+            serde_json::to_string_pretty(state).context("Failed to serialize alert state")?;
+        crate::storage::write_atomic(&self.state_path, content.as_bytes())
+            .context("Failed to write alert state")
+    }
+
+    /// Every configured target, regardless of which alerts it subscribes to
+    pub fn all_targets(&self) -> Vec<Target> {
+        let mut targets = Vec::new();
+        if self.config.desktop {
+            targets.push(Target::Desktop);
+        }
+        if !self.config.command.is_empty() {
+            targets.push(Target::Command(self.config.command.clone()));
+        }
+        targets.extend(self.config.webhooks.iter().cloned().map(Target::Webhook));
+        targets
+    }
+
+    /// Targets configured to receive this alert
+    pub fn targets(&self, alert: &Alert) -> Vec<Target> {
+        self.all_targets()
+            .into_iter()
+            .filter(|target| target.accepts(alert))
+            .collect()
+    }
+
+    /// Deliver alerts to every configured target in the background.
+    /// Await the returned handles before exiting if the process is about to end.
+    pub fn notify(&self, alerts: &[Alert]) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = Vec::new();
+        for alert in alerts {
+            for target in self.targets(alert) {
+                let alert = alert.clone();
+                let display = self.display.clone();
+                let client = self.client.clone();
+                handles.push(tokio::spawn(async move {
+                    if let Err(e) = target.deliver(&alert, &display, &client).await {
+                        tracing::warn!("Failed to send alert via {}: {:#}", target.describe(), e);
+                    }
+                }));
+            }
+        }
+        handles
+    }
+
+    /// Observe a batch of results and deliver new alerts
+    pub fn process<'a>(
+        &self,
+        results: impl IntoIterator<Item = (&'a Account, &'a Result<QuotaInfo>)>,
+    ) -> Vec<tokio::task::JoinHandle<()>> {
+        let alerts = self.observe(results);
+        self.notify(&alerts)
+    }
+}
+
+/// A made-up alert for checking that targets are set up correctly
+pub fn sample_alert() -> Alert {
+    Alert::Threshold {
+        account: "example".to_string(),
+        provider: "openrouter".to_string(),
+        metric: "cost",
+        threshold: 80.0,
+        percent: 85.0,
+        used: 170.0,
+        limit: 200.0,
+        reset_date: None,
+        timestamp: chrono::Utc::now(),
+    }
+}
+
+/// Show a notification through `notify-send`, which talks to the desktop's D-Bus service
+fn send_desktop_notification(alert: &Alert, display: &DisplayConfig) -> Result<()> {
+    let urgency = if alert.is_urgent(display) {
+        "critical"
+    } else {
+        "normal"
+    };
+    let status = Command::new("notify-send")
+        .arg("--app-name=tokstat")
+        .arg(format!("--urgency={}", urgency))
+        .arg(alert.title())
+        .arg(alert.summary(display))
+        .status()
+        .context("Failed to run notify-send")?;
+    if !status.success() {
+        anyhow::bail!("notify-send exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Run the user's command through the shell with the alert as JSON on stdin
+fn run_alert_command(command: &str, alert: &Alert, display: &DisplayConfig) -> Result<()> {
+    let payload =
+        serde_json::to_vec(&alert.payload(display)).context("Failed to serialize alert")?;
+
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()
+    }
+    .with_context(|| format!("Failed to start '{}'", command))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command may ignore its input, so a closed pipe is fine
+        let _ = stdin.write_all(&payload);
+    }
+    let status = child.wait().context("Failed to wait for alert command")?;
+    if !status.success() {
+        anyhow::bail!("'{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+/// POST the alert in the webhook's format
+async fn post_webhook(
+    client: &HttpClient,
+    webhook: &WebhookConfig,
+    alert: &Alert,
+    display: &DisplayConfig,
+) -> Result<()> {
+    let body = match webhook.format {
+        WebhookFormat::Json => alert.payload(display),
+        WebhookFormat::Slack => slack_message(alert, display),
+        WebhookFormat::Teams => teams_message(alert, display),
+    };
+
+    let response = client
+        .send(client.post(&webhook.url).json(&body))
+        .await
+        .with_context(|| format!("Failed to reach {}", webhook.url))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} answered {}: {}", webhook.url, status, text.trim());
+    }
+    Ok(())
+}
+
+/// Slack incoming webhook message
+fn slack_message(alert: &Alert, display: &DisplayConfig) -> serde_json::Value {
+    let emoji = match alert {
+        Alert::Reset { .. } => ":recycle:",
+        _ if alert.is_urgent(display) => ":rotating_light:",
+        _ => ":warning:",
+    };
+    serde_json::json!({
+        "text": format!("{} {}", emoji, alert.summary(display)),
+    })
+}
+
+/// Microsoft Teams message with an Adaptive Card, as accepted by Teams workflow webhooks
+fn teams_message(alert: &Alert, display: &DisplayConfig) -> serde_json::Value {
+    let color = match alert {
+        Alert::Reset { .. } => "Good",
+        _ if alert.is_urgent(display) => "Attention",
+        _ => "Warning",
+    };
+    serde_json::json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": [
+                    {
+                        "type": "TextBlock",
+                        "text": alert.title(),
+                        "weight": "Bolder",
+                        "size": "Medium",
+                        "color": color,
+                    },
+                    {
+                        "type": "TextBlock",
+                        "text": alert.summary(display),
+                        "wrap": true,
+                    },
+                ],
+            },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HttpConfig;
+    use crate::providers::{TokenLimits, TokenUsage};
+    use crate::testing::{body, client, Stub};
+
+    fn alerter(dir: &tempfile::TempDir) -> Alerter {
+        Alerter {
+            config: AlertsConfig {
+                thresholds: vec![50.0, 80.0, 95.0],
+                ..Default::default()
+            },
+            display: DisplayConfig::default(),
+            client: HttpClient::new(&Default::default()).unwrap(),
+            state_path: dir.path().join("alert_state.json"),
+        }
+    }
+
+    fn account() -> Account {
+        Account {
+            name: "work".to_string(),
+            provider: "openrouter".to_string(),
+            email: None,
+            created_at: chrono::Utc::now(),
+            last_updated: chrono::Utc::now(),
+            budget: None,
+            base_url: None,
+            credentials_from: None,
+        }
+    }
+
+    /// `cost` dollars spent of a $100 limit
+    fn quota(cost: f64, reset_date: Option<&str>) -> Result<QuotaInfo> {
+        Ok(QuotaInfo {
+            provider: "openrouter".to_string(),
+            account_name: "work".to_string(),
+            usage: TokenUsage {
+                tokens_used: None,
+                requests_made: None,
+                cost: Some(cost),
+            },
+            limits: Some(TokenLimits {
+                max_cost: Some(100.0),
+                ..Default::default()
+            }),
+            reset_date: reset_date.map(|date| {
+                chrono::DateTime::parse_from_rfc3339(date)
+                    .unwrap()
+                    .with_timezone(&chrono::Utc)
+            }),
+            last_updated: chrono::Utc::now(),
+        })
+    }
+
+    fn observe(alerter: &Alerter, result: Result<QuotaInfo>) -> Vec<Alert> {
+        alerter.observe([(&account(), &result)])
+    }
+
+    fn thresholds(alerts: &[Alert]) -> Vec<f64> {
+        alerts
+            .iter()
+            .map(|alert| match alert {
+                Alert::Threshold { threshold, .. } => *threshold,
+                other => panic!("unexpected alert {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threshold_fires_once_and_again_only_when_higher() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerter = alerter(&dir);
+
+        assert_eq!(thresholds(&observe(&alerter, quota(55.0, None))), [50.0]);
+        assert!(observe(&alerter, quota(60.0, None)).is_empty());
+        // Jumping past several thresholds announces only the highest
+        assert_eq!(thresholds(&observe(&alerter, quota(97.0, None))), [95.0]);
+        assert!(observe(&alerter, quota(99.0, None)).is_empty());
+    }
+
+    #[test]
+    fn threshold_fires_again_after_usage_drops() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerter = alerter(&dir);
+
+        assert_eq!(thresholds(&observe(&alerter, quota(85.0, None))), [80.0]);
+        assert!(observe(&alerter, quota(10.0, None)).is_empty());
+        assert_eq!(thresholds(&observe(&alerter, quota(85.0, None))), [80.0]);
+    }
+
+    #[test]
+    fn announced_thresholds_are_shared_through_the_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            thresholds(&observe(&alerter(&dir), quota(85.0, None))),
+            [80.0]
+        );
+        assert!(observe(&alerter(&dir), quota(85.0, None)).is_empty());
+    }
+
+    #[test]
+    fn reset_fires_when_the_reset_date_moves_past_a_passed_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerter = alerter(&dir);
+
+        // Nothing to compare with on the first fetch
+        assert!(observe(&alerter, quota(0.0, Some("2020-01-01T00:00:00Z"))).is_empty());
+        let alerts = observe(&alerter, quota(0.0, Some("2099-01-01T00:00:00Z")));
+        assert!(matches!(alerts.as_slice(), [Alert::Reset { .. }]));
+        // A reset date still in the future moving on isn't a reset
+        assert!(observe(&alerter, quota(0.0, Some("2099-02-01T00:00:00Z"))).is_empty());
+    }
+
+    #[test]
+    fn fetch_failure_fires_only_when_it_starts_failing() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerter = alerter(&dir);
+        let failed = || Err(anyhow::anyhow!("401 Unauthorized"));
+
+        let alerts = observe(&alerter, failed());
+        assert!(matches!(alerts.as_slice(), [Alert::FetchFailed { .. }]));
+        assert!(observe(&alerter, failed()).is_empty());
+        assert!(observe(&alerter, quota(0.0, None)).is_empty());
+        assert_eq!(observe(&alerter, failed()).len(), 1);
+    }
+
+    #[test]
+    fn offline_cache_miss_is_not_a_fetch_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let alerter = alerter(&dir);
+        let not_cached = Err(crate::providers::NotCached {
+            account_name: "work".to_string(),
+        }
+        .into());
+
+        assert!(observe(&alerter, not_cached).is_empty());
+        let alerts = observe(&alerter, Err(anyhow::anyhow!("timed out")));
+        assert!(matches!(alerts.as_slice(), [Alert::FetchFailed { .. }]));
+    }
+
+    #[test]
+    fn urgency_follows_the_critical_threshold() {
+        let display = DisplayConfig {
+            critical_threshold: 90.0,
+            ..Default::default()
+        };
+        let alert = |percent| Alert::Threshold {
+            account: "work".to_string(),
+            provider: "openrouter".to_string(),
+            metric: "cost",
+            threshold: 80.0,
+            percent,
+            used: percent,
+            limit: 100.0,
+            reset_date: None,
+            timestamp: chrono::Utc::now(),
+        };
+
+        assert!(!alert(85.0).is_urgent(&display));
+        assert!(alert(92.0).is_urgent(&display));
+    }
+
+    fn webhook(url: String, format: WebhookFormat) -> Target {
+        Target::Webhook(WebhookConfig {
+            url,
+            format,
+            events: AlertKind::ALL.to_vec(),
+            accounts: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn json_webhook_posts_alert_with_summary() {
+        let stub = Stub::start(&[("/hook", 200, "{}")]).await;
+        let target = webhook(format!("{}/hook", stub.base_url), WebhookFormat::Json);
+
+        target
+            .deliver(&sample_alert(), &Default::default(), &client())
+            .await
+            .unwrap();
+
+        let requests = stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("POST /hook "));
+        let body = body(&requests[0]);
+        assert_eq!(body["kind"], "threshold");
+        assert_eq!(body["account"], "example");
+        assert_eq!(
+            body["summary"],
+            "example at 85% of its cost limit ($170.00/$200.00)"
+        );
+    }
+
+    #[tokio::test]
+    async fn slack_and_teams_webhooks_use_their_message_formats() {
+        let stub = Stub::start(&[("/slack", 200, "ok"), ("/teams", 202, "")]).await;
+        let alert = sample_alert();
+
+        for (path, format) in [
+            ("slack", WebhookFormat::Slack),
+            ("teams", WebhookFormat::Teams),
+        ] {
+            webhook(format!("{}/{}", stub.base_url, path), format)
+                .deliver(&alert, &Default::default(), &client())
+                .await
+                .unwrap();
+        }
+
+        let requests = stub.requests();
+        let slack = body(&requests[0]);
+        assert!(slack["text"]
+            .as_str()
+            .unwrap()
+            .ends_with("($170.00/$200.00)"));
+        let teams = body(&requests[1]);
+        assert_eq!(teams["type"], "message");
+        assert_eq!(
+            teams["attachments"][0]["content"]["body"][0]["text"],
+            "example: cost above 80%"
+        );
+    }
+
+    #[tokio::test]
+    async fn webhook_uses_configured_user_agent() {
+        let stub = Stub::start(&[("/hook", 200, "{}")]).await;
+        let client = HttpClient::new(&HttpConfig {
+            retries: 0,
+            user_agent: "acme-monitoring/1.0".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        webhook(format!("{}/hook", stub.base_url), WebhookFormat::Json)
+            .deliver(&sample_alert(), &Default::default(), &client)
+            .await
+            .unwrap();
+
+        assert!(stub.requests()[0]
+            .to_lowercase()
+            .contains("user-agent: acme-monitoring/1.0"));
+    }
+
+    #[tokio::test]
+    async fn webhook_reports_http_errors() {
+        let stub = Stub::start(&[("/hook", 403, r#"{"error":"invalid token"}"#)]).await;
+
+        let error = webhook(format!("{}/hook", stub.base_url), WebhookFormat::Json)
+            .deliver(&sample_alert(), &Default::default(), &client())
+            .await
+            .unwrap_err();
+
+        let message = error.to_string();
+        assert!(message.contains("403"), "{}", message);
+        assert!(message.contains("invalid token"), "{}", message);
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+
+use crate::providers::anthropic::AnthropicProvider;
+
+pub async fn login(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account_name: &str,
+    options: &super::LoginOptions,
+) -> Result<()> {
+    println!("\n🔐 Anthropic Login\n");
+    println!("tokstat reads the Admin usage and cost reports, so an Admin API key is required.");
+    println!("Create one at: https://console.anthropic.com/settings/admin-keys\n");
+
+    let api_key = super::api_key(options, "Enter your Anthropic Admin API key: ")?;
+
+    if options.no_validate {
+        println!("\nSkipping API key validation");
+    } else {
+        // Validate the key with a minimal usage report request
+        println!("\nValidating API key...");
+
+        let provider = match &options.base_url {
+            Some(url) => AnthropicProvider::with_base_url(url),
+            None => AnthropicProvider::new(),
+        };
+        let starting_at = crate::providers::current_month_period(chrono::Utc::now())
+            .0
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+        let request = client
+            .get(format!(
+                "{}/v1/organizations/usage_report/messages",
+                provider.base_url()
+            ))
+            .header("x-api-key", &api_key)
+            .header("anthropic-version", "2023-06-01")
+            .query(&[("starting_at", starting_at.as_str()), ("limit", "1")]);
+        let response = client
+            .send(request)
+            .await
+            .context("Failed to validate API key")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Invalid Admin API key: {}", response.status());
+        }
+
+        println!("✓ API key validated successfully!");
+    }
+
+    // Store credentials
+    let credentials = crate::providers::anthropic::AnthropicCredentials { api_key };
+
+    let credentials_json =
+        serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
+
+    storage
+        .store_credentials(account_name, &credentials_json, !options.non_interactive)
+        .context("Failed to store credentials")?;
+
+    // Store account metadata
+    let account = crate::storage::Account {
+        name: account_name.to_string(),
+        provider: "anthropic".to_string(),
+        email: None,
+        created_at: chrono::Utc::now(),
+        last_updated: chrono::Utc::now(),
+        budget: None,
+        base_url: options.base_url.clone(),
+        credentials_from: None,
+    };
+
+    storage
+        .save_account(account)
+        .context("Failed to save account")?;
+
+    Ok(())
+}
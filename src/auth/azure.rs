@@ -1,73 +1,74 @@
 use anyhow::{Context, Result};
-use std::io::{self, Write};
 
-pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str) -> Result<()> {
+use crate::providers::azure::AzureProvider;
+
+pub async fn login(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account_name: &str,
+    options: &super::LoginOptions,
+) -> Result<()> {
     println!("\n🔐 Azure OpenAI Login\n");
     println!("You need your Azure OpenAI API key and resource name.");
     println!("Find them in the Azure Portal under your Azure OpenAI resource.\n");
 
-    // Get resource name (check env var first)
-    let resource_name = match std::env::var("AZURE_RESOURCE_NAME") {
-        Ok(name) if !name.is_empty() => {
-            println!("Using resource name from AZURE_RESOURCE_NAME: {}", name);
-            name
-        }
-        _ => {
-            print!("Enter your Azure resource name: ");
-            io::stdout().flush()?;
-
-            let mut resource_name = String::new();
-            io::stdin()
-                .read_line(&mut resource_name)
-                .context("Failed to read resource name")?;
-
-            let resource_name = resource_name.trim().to_string();
+    let provider = match &options.base_url {
+        Some(url) => AzureProvider::with_base_url(url),
+        None => AzureProvider::new(),
+    };
 
-            if resource_name.is_empty() {
-                anyhow::bail!("Resource name cannot be empty");
+    // A custom endpoint replaces the resource's own, so its name isn't needed
+    let resource_name = if let Some(name) = &options.resource_name {
+        name.clone()
+    } else if let Some(url) = provider.base_url() {
+        println!("Using endpoint {}", url);
+        String::new()
+    } else {
+        // Get resource name (check env var first)
+        match std::env::var("AZURE_RESOURCE_NAME") {
+            Ok(name) if !name.is_empty() => {
+                println!("Using resource name from AZURE_RESOURCE_NAME: {}", name);
+                name
             }
-
-            resource_name
+            _ if options.non_interactive => {
+                anyhow::bail!("No resource name given, pass --resource-name")
+            }
+            _ => super::read_line("Enter your Azure resource name: ")
+                .context("Failed to read resource name")?,
         }
     };
 
-    print!("Enter your Azure OpenAI API key: ");
-    io::stdout().flush()?;
-
-    let mut api_key = String::new();
-    io::stdin()
-        .read_line(&mut api_key)
-        .context("Failed to read API key")?;
-
-    let api_key = api_key.trim().to_string();
-
-    if api_key.is_empty() {
-        anyhow::bail!("API key cannot be empty");
+    if resource_name.is_empty() && provider.base_url().is_none() {
+        anyhow::bail!("Resource name cannot be empty");
     }
 
-    // Validate by listing models (data-plane endpoint available with api-key auth)
-    println!("\nValidating credentials...");
-
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://{}.openai.azure.com/openai/models?api-version=2024-06-01",
-        resource_name
-    );
-
-    let response = client
-        .get(&url)
-        .header("api-key", &api_key)
-        .send()
-        .await
-        .context("Failed to connect to Azure OpenAI")?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        anyhow::bail!("Invalid credentials: {} - {}", status, body);
-    }
+    let api_key = super::api_key(options, "Enter your Azure OpenAI API key: ")?;
+
+    if options.no_validate {
+        println!("\nSkipping credential validation");
+    } else {
+        // Validate by listing models (data-plane endpoint available with api-key auth)
+        println!("\nValidating credentials...");
+
+        let url = format!(
+            "{}/openai/models?api-version=2024-06-01",
+            provider.endpoint(&resource_name)?
+        );
+
+        let request = client.get(&url).header("api-key", &api_key);
+        let response = client
+            .send(request)
+            .await
+            .context("Failed to connect to Azure OpenAI")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Invalid credentials: {} - {}", status, body);
+        }
 
-    println!("✓ Credentials validated successfully!");
+        println!("✓ Credentials validated successfully!");
+    }
 
     // Store credentials
     let credentials = crate::providers::azure::AzureCredentials {
@@ -79,7 +80,7 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
         serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
 
     storage
-        .store_credentials(account_name, &credentials_json)
+        .store_credentials(account_name, &credentials_json, !options.non_interactive)
         .context("Failed to store credentials")?;
 
     // Store account metadata
@@ -89,6 +90,10 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
         email: None,
         created_at: chrono::Utc::now(),
         last_updated: chrono::Utc::now(),
+        budget: None,
+        // Keep an endpoint from AZURE_OPENAI_ENDPOINT too, the account has no resource name
+        base_url: provider.base_url().map(String::from),
+        credentials_from: None,
     };
 
     storage
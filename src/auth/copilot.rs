@@ -101,21 +101,263 @@ fn copy_to_clipboard(text: &str) -> Result<()> {
     }
 }
 
-pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str) -> Result<()> {
+pub async fn login(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account_name: &str,
+    options: &super::LoginOptions,
+) -> Result<()> {
     println!("\n🔐 GitHub Copilot Login Flow\n");
 
-    let client = reqwest::Client::new();
+    let access_token = match &options.github_token {
+        Some(token) => token.clone(),
+        None if options.non_interactive => {
+            anyhow::bail!(
+                "The device flow needs a browser, pass --github-token-env to log in without one"
+            )
+        }
+        None => device_flow(client).await?,
+    };
+
+    let email = if options.no_validate {
+        println!("Skipping token validation");
+        None
+    } else {
+        Some(validate_token(client, &access_token, options.base_url.as_deref()).await?)
+    };
+
+    store_token(
+        storage,
+        account_name,
+        access_token,
+        !options.non_interactive,
+    )?;
+
+    // Store account metadata
+    let account = crate::storage::Account {
+        name: account_name.to_string(),
+        provider: "copilot".to_string(),
+        email,
+        created_at: chrono::Utc::now(),
+        last_updated: chrono::Utc::now(),
+        budget: None,
+        base_url: options.base_url.clone(),
+        credentials_from: None,
+    };
+
+    storage
+        .save_account(account)
+        .context("Failed to save account")?;
+
+    Ok(())
+}
+
+/// Run the device flow again for an existing account, e.g. after its token was revoked.
+///
+/// Only the token and the GitHub user change; the account keeps its position, budget,
+/// base URL and quota history.
+pub async fn reauth(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account: &crate::storage::Account,
+) -> Result<()> {
+    println!("\n🔐 GitHub Copilot Login Flow for '{}'\n", account.name);
+
+    let access_token = device_flow(client).await?;
+    let email = validate_token(client, &access_token, account.base_url.as_deref()).await?;
+
+    store_token(storage, &account.name, access_token, true)?;
+
+    let mut account = account.clone();
+    account.email = Some(email);
+    account.last_updated = chrono::Utc::now();
+    storage
+        .update_account(account)
+        .context("Failed to save account")?;
+
+    Ok(())
+}
+
+/// Check the token against the GitHub API and return its user for `Account.email`
+async fn validate_token(
+    client: &crate::http::HttpClient,
+    access_token: &str,
+    base_url: Option<&str>,
+) -> Result<String> {
+    use crate::providers::copilot::CopilotProvider;
+
+    println!("Validating GitHub token...");
+
+    let provider = match base_url {
+        Some(url) => CopilotProvider::with_base_url(url),
+        None => CopilotProvider::new(),
+    };
+    let user = provider
+        .fetch_user(client, access_token)
+        .await
+        .context("Invalid GitHub token")?;
+
+    println!("✓ Signed in as {}", user.describe());
+    Ok(user.describe())
+}
+
+fn store_token(
+    storage: &crate::storage::SecureStorage,
+    account_name: &str,
+    access_token: String,
+    interactive: bool,
+) -> Result<()> {
+    // GitHub's device flow has no refresh token, the token is valid until it is revoked
+    let credentials = crate::providers::copilot::CopilotCredentials { access_token };
+
+    let credentials_json =
+        serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
+
+    storage
+        .store_credentials(account_name, &credentials_json, interactive)
+        .context("Failed to store credentials")
+}
+
+/// Where `--import-token` looks for a GitHub token that is already on this machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TokenSource {
+    /// The first of `env`, `editor` and `gh` that has a token
+    Auto,
+    /// The `GH_TOKEN` environment variable
+    Env,
+    /// The Copilot editor plugin's `apps.json` or `hosts.json`
+    Editor,
+    /// `gh auth token` from the GitHub CLI
+    Gh,
+}
+
+/// Find an existing GitHub token, returning it with a description of where it came from
+pub fn import_token(source: TokenSource) -> Result<(String, String)> {
+    let sources = match source {
+        TokenSource::Auto => vec![TokenSource::Env, TokenSource::Editor, TokenSource::Gh],
+        source => vec![source],
+    };
+
+    let mut failures = Vec::new();
+    for source in sources {
+        let result = match source {
+            TokenSource::Env => {
+                super::read_secret_env("GH_TOKEN").map(|token| (token, "GH_TOKEN".to_string()))
+            }
+            TokenSource::Editor => editor_token(),
+            TokenSource::Gh => gh_token(),
+            TokenSource::Auto => unreachable!("auto is expanded above"),
+        };
+        match result {
+            Ok(found) => return Ok(found),
+            Err(e) => failures.push(format!("{:#}", e)),
+        }
+    }
+
+    anyhow::bail!("No GitHub token found:\n  {}", failures.join("\n  "))
+}
 
+/// Token the Copilot editor plugins (VS Code, JetBrains, Neovim) stored for github.com
+fn editor_token() -> Result<(String, String)> {
+    let dir = copilot_config_dir().context("Failed to find the Copilot config directory")?;
+
+    // apps.json replaced hosts.json in newer plugin versions, both may exist
+    for file in ["apps.json", "hosts.json"] {
+        let path = dir.join(file);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let token =
+            parse_hosts(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some(token) = token {
+            return Ok((token, path.display().to_string()));
+        }
+    }
+
+    anyhow::bail!(
+        "No github.com token in {} or {}",
+        dir.join("apps.json").display(),
+        dir.join("hosts.json").display()
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct CopilotHost {
+    oauth_token: Option<String>,
+}
+
+/// github.com token from the contents of `apps.json` or `hosts.json`.
+///
+/// Keys are `github.com` in hosts.json and `github.com:<client id>` in apps.json, which can
+/// hold one entry per OAuth app. The Copilot app is preferred, then the first by client id,
+/// so the same token is picked on every run.
+fn parse_hosts(content: &str) -> Result<Option<String>> {
+    let hosts: std::collections::BTreeMap<String, CopilotHost> = serde_json::from_str(content)?;
+    let copilot_app = format!("github.com:{}", GITHUB_CLIENT_ID);
+    let rank = |host: &str| match host {
+        "github.com" => Some(0),
+        _ if host == copilot_app => Some(1),
+        _ if host.starts_with("github.com:") => Some(2),
+        _ => None,
+    };
+
+    Ok(hosts
+        .into_iter()
+        .filter_map(|(host, entry)| {
+            let token = entry.oauth_token.filter(|token| !token.is_empty())?;
+            Some((rank(&host)?, token))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, token)| token))
+}
+
+/// `~/.config/github-copilot`, or `%LOCALAPPDATA%\github-copilot` on Windows
+fn copilot_config_dir() -> Option<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    if cfg!(windows) {
+        return std::env::var_os("LOCALAPPDATA")
+            .map(|dir| PathBuf::from(dir).join("github-copilot"));
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("github-copilot"))
+}
+
+/// Token of the account the GitHub CLI is logged in with
+fn gh_token() -> Result<(String, String)> {
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", "github.com"])
+        .output()
+        .context("Failed to run `gh auth token`, is the GitHub CLI installed?")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("`gh auth token` failed: {}", stderr.trim());
+    }
+
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        anyhow::bail!("`gh auth token` printed no token");
+    }
+    Ok((token, "gh auth token".to_string()))
+}
+
+/// Run GitHub's OAuth device flow and return the access token
+async fn device_flow(client: &crate::http::HttpClient) -> Result<String> {
     // Step 1: Request device code
     println!("Requesting device code...");
-    let device_response = client
+    let device_request = client
         .post(GITHUB_DEVICE_CODE_URL)
         .header("Accept", "application/json")
         .form(&DeviceCodeRequest {
             client_id: GITHUB_CLIENT_ID.to_string(),
             scope: GITHUB_SCOPES.to_string(),
-        })
-        .send()
+        });
+    let device_response = client
+        .send(device_request)
         .await
         .context("Failed to request device code")?;
 
@@ -179,15 +421,16 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
     for _attempt in 0..max_attempts {
         sleep(interval).await;
 
-        let token_response = client
+        let token_request = client
             .post(GITHUB_TOKEN_URL)
             .header("Accept", "application/json")
             .form(&AccessTokenRequest {
                 client_id: GITHUB_CLIENT_ID.to_string(),
                 device_code: device_code_data.device_code.clone(),
                 grant_type: "urn:ietf:params:oauth:grant-type:device_code".to_string(),
-            })
-            .send()
+            });
+        let token_response = client
+            .send(token_request)
             .await
             .context("Failed to request access token")?;
 
@@ -235,35 +478,49 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
 
     println!("\n✓ Authorization successful!");
 
-    // Now we need to exchange this for Copilot tokens
-    // This part depends on GitHub's Copilot API authentication
-    // For now, we'll store the GitHub token
-
-    let credentials = crate::providers::copilot::CopilotCredentials {
-        access_token: access_token.clone(),
-        refresh_token: String::new(), // GitHub doesn't provide refresh tokens with device flow
-        expires_at: chrono::Utc::now() + chrono::Duration::days(90), // GitHub tokens don't expire by default
-    };
+    Ok(access_token)
+}
 
-    let credentials_json =
-        serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    storage
-        .store_credentials(account_name, &credentials_json)
-        .context("Failed to store credentials")?;
+    #[test]
+    fn parse_hosts_reads_hosts_json() {
+        let content = r#"{"github.com": {"user": "octocat", "oauth_token": "gho_hosts"}}"#;
+        assert_eq!(parse_hosts(content).unwrap().as_deref(), Some("gho_hosts"));
+    }
 
-    // Store account metadata
-    let account = crate::storage::Account {
-        name: account_name.to_string(),
-        provider: "copilot".to_string(),
-        email: None,
-        created_at: chrono::Utc::now(),
-        last_updated: chrono::Utc::now(),
-    };
+    #[test]
+    fn parse_hosts_prefers_the_copilot_app_in_apps_json() {
+        let content = format!(
+            r#"{{
+                "github.com:Iv1.aaaaaaaaaaaaaaaa": {{"user": "octocat", "oauth_token": "gho_other"}},
+                "github.com:{}": {{"user": "octocat", "oauth_token": "gho_copilot"}},
+                "github.com:Iv1.zzzzzzzzzzzzzzzz": {{"user": "octocat", "oauth_token": "gho_last"}}
+            }}"#,
+            GITHUB_CLIENT_ID
+        );
+        assert_eq!(
+            parse_hosts(&content).unwrap().as_deref(),
+            Some("gho_copilot")
+        );
+    }
 
-    storage
-        .save_account(account)
-        .context("Failed to save account")?;
+    #[test]
+    fn parse_hosts_picks_other_apps_by_client_id() {
+        let content = r#"{
+            "github.com:Iv1.zzzzzzzzzzzzzzzz": {"oauth_token": "gho_last"},
+            "github.com:Iv1.bbbbbbbbbbbbbbbb": {"oauth_token": ""},
+            "github.com:Iv1.cccccccccccccccc": {"oauth_token": "gho_first"}
+        }"#;
+        assert_eq!(parse_hosts(content).unwrap().as_deref(), Some("gho_first"));
+    }
 
-    Ok(())
+    #[test]
+    fn parse_hosts_ignores_other_hosts() {
+        let content = r#"{"ghe.example.com": {"oauth_token": "gho_enterprise"}}"#;
+        assert_eq!(parse_hosts(content).unwrap(), None);
+        assert!(parse_hosts("not json").is_err());
+    }
 }
@@ -1,3 +1,88 @@
+use anyhow::{Context, Result};
+use std::io::{self, Read, Write};
+
+pub mod anthropic;
 pub mod azure;
 pub mod copilot;
+pub mod openai;
 pub mod openrouter;
+
+/// Settings given to `tokstat login` that apply to every provider
+#[derive(Debug, Clone, Default)]
+pub struct LoginOptions {
+    /// API base URL stored with the account, used for validation and every fetch
+    pub base_url: Option<String>,
+    /// API key from `--api-key-stdin` or `--api-key-env`, asked for when unset
+    pub api_key: Option<String>,
+    /// GitHub token from `--github-token-env` or `--import-token`, used instead of Copilot's
+    /// device flow
+    pub github_token: Option<String>,
+    /// Azure resource name from `--resource-name`
+    pub resource_name: Option<String>,
+    /// Store the credentials without checking them against the provider
+    pub no_validate: bool,
+    /// Fail instead of prompting for anything that wasn't given (`--yes`)
+    pub non_interactive: bool,
+}
+
+impl LoginOptions {
+    /// Reject options that don't apply to `provider` instead of silently ignoring them
+    pub fn check(&self, provider: &str) -> Result<()> {
+        if self.api_key.is_some() && provider == "copilot" {
+            anyhow::bail!("Copilot logs in with a GitHub token, use --github-token-env instead");
+        }
+        if self.github_token.is_some() && provider != "copilot" {
+            anyhow::bail!("--github-token-env only applies to copilot");
+        }
+        if self.resource_name.is_some() && provider != "azure" {
+            anyhow::bail!("--resource-name only applies to azure");
+        }
+        Ok(())
+    }
+}
+
+/// Read a secret from standard input, e.g. piped from a password manager
+pub fn read_secret_stdin() -> Result<String> {
+    let mut secret = String::new();
+    io::stdin()
+        .read_to_string(&mut secret)
+        .context("Failed to read from standard input")?;
+    Ok(secret.trim().to_string())
+}
+
+/// Read a secret from the environment variable `name`
+pub fn read_secret_env(name: &str) -> Result<String> {
+    match std::env::var(name) {
+        Ok(secret) if !secret.trim().is_empty() => Ok(secret.trim().to_string()),
+        Ok(_) => anyhow::bail!("Environment variable {} is empty", name),
+        Err(_) => anyhow::bail!("Environment variable {} is not set", name),
+    }
+}
+
+/// The API key given on the command line, or else asked for with `prompt`
+fn api_key(options: &LoginOptions, prompt: &str) -> Result<String> {
+    let api_key = match &options.api_key {
+        Some(api_key) => api_key.clone(),
+        None if options.non_interactive => {
+            anyhow::bail!("No API key given, pass --api-key-stdin or --api-key-env")
+        }
+        None => read_line(prompt).context("Failed to read API key")?,
+    };
+
+    if api_key.is_empty() {
+        anyhow::bail!("API key cannot be empty");
+    }
+
+    Ok(api_key)
+}
+
+/// Print `prompt` and read one trimmed line from standard input
+fn read_line(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    Ok(line.trim().to_string())
+}
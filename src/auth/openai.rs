@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+
+use crate::providers::openai::OpenAIProvider;
+
+pub async fn login(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account_name: &str,
+    options: &super::LoginOptions,
+) -> Result<()> {
+    println!("\n🔐 OpenAI Login\n");
+    println!("tokstat reads the organization Usage and Costs APIs, so an admin key is required.");
+    println!("Create one at: https://platform.openai.com/settings/organization/admin-keys\n");
+
+    let api_key = super::api_key(options, "Enter your OpenAI admin key: ")?;
+
+    // Optional, so it's left out when scripted: a piped key has already used up stdin
+    let budget = if options.non_interactive || options.api_key.is_some() {
+        String::new()
+    } else {
+        super::read_line("Monthly budget in USD (optional, press Enter to skip): ")
+            .context("Failed to read monthly budget")?
+    };
+
+    let budget = budget.trim_start_matches('$');
+    // Kept as a regular manual budget, so `tokstat budget` can show and change it
+    let budget = if budget.is_empty() {
+        None
+    } else {
+        let value: f64 = budget
+            .parse()
+            .context(format!("Invalid monthly budget '{}'", budget))?;
+        if value <= 0.0 {
+            anyhow::bail!("Monthly budget must be greater than zero");
+        }
+        Some(crate::storage::Budget {
+            limits: crate::providers::TokenLimits {
+                max_cost: Some(value),
+                ..Default::default()
+            },
+        })
+    };
+
+    if options.no_validate {
+        println!("\nSkipping API key validation");
+    } else {
+        // Validate the key with a minimal usage request
+        println!("\nValidating API key...");
+
+        let provider = match &options.base_url {
+            Some(url) => OpenAIProvider::with_base_url(url),
+            None => OpenAIProvider::new(),
+        };
+        let start_time = crate::providers::current_month_period(chrono::Utc::now())
+            .0
+            .timestamp()
+            .to_string();
+
+        let request = client
+            .get(format!(
+                "{}/v1/organization/usage/completions",
+                provider.base_url()
+            ))
+            .header("Authorization", format!("Bearer {}", api_key))
+            .query(&[("start_time", start_time.as_str()), ("limit", "1")]);
+        let response = client
+            .send(request)
+            .await
+            .context("Failed to validate API key")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Invalid admin key: {}", response.status());
+        }
+
+        println!("✓ API key validated successfully!");
+    }
+
+    // Store credentials
+    let credentials = crate::providers::openai::OpenAICredentials { api_key };
+
+    let credentials_json =
+        serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
+
+    storage
+        .store_credentials(account_name, &credentials_json, !options.non_interactive)
+        .context("Failed to store credentials")?;
+
+    // Store account metadata
+    let account = crate::storage::Account {
+        name: account_name.to_string(),
+        provider: "openai".to_string(),
+        email: None,
+        created_at: chrono::Utc::now(),
+        last_updated: chrono::Utc::now(),
+        budget,
+        base_url: options.base_url.clone(),
+        credentials_from: None,
+    };
+
+    storage
+        .save_account(account)
+        .context("Failed to save account")?;
+
+    Ok(())
+}
@@ -1,41 +1,43 @@
 use anyhow::{Context, Result};
-use std::io::{self, Write};
 
-pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str) -> Result<()> {
+use crate::providers::openrouter::OpenRouterProvider;
+
+pub async fn login(
+    storage: &crate::storage::SecureStorage,
+    client: &crate::http::HttpClient,
+    account_name: &str,
+    options: &super::LoginOptions,
+) -> Result<()> {
     println!("\n🔐 OpenRouter Login\n");
     println!("You can find your API key at: https://openrouter.ai/keys\n");
 
-    print!("Enter your OpenRouter API key: ");
-    io::stdout().flush()?;
-
-    let mut api_key = String::new();
-    io::stdin()
-        .read_line(&mut api_key)
-        .context("Failed to read API key")?;
+    let api_key = super::api_key(options, "Enter your OpenRouter API key: ")?;
 
-    let api_key = api_key.trim().to_string();
+    if options.no_validate {
+        println!("\nSkipping API key validation");
+    } else {
+        // Validate the API key by making a test request
+        println!("\nValidating API key...");
 
-    if api_key.is_empty() {
-        anyhow::bail!("API key cannot be empty");
-    }
+        let provider = match &options.base_url {
+            Some(url) => OpenRouterProvider::with_base_url(url),
+            None => OpenRouterProvider::new(),
+        };
+        let request = client
+            .get(format!("{}/auth/key", provider.base_url()))
+            .header("Authorization", format!("Bearer {}", api_key));
+        let response = client
+            .send(request)
+            .await
+            .context("Failed to validate API key")?;
 
-    // Validate the API key by making a test request
-    println!("\nValidating API key...");
+        if !response.status().is_success() {
+            anyhow::bail!("Invalid API key: {}", response.status());
+        }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get("https://openrouter.ai/api/v1/auth/key")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send()
-        .await
-        .context("Failed to validate API key")?;
-
-    if !response.status().is_success() {
-        anyhow::bail!("Invalid API key: {}", response.status());
+        println!("✓ API key validated successfully!");
     }
 
-    println!("✓ API key validated successfully!");
-
     // Store credentials
     let credentials = crate::providers::openrouter::OpenRouterCredentials {
         api_key: api_key.clone(),
@@ -45,7 +47,7 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
         serde_json::to_string(&credentials).context("Failed to serialize credentials")?;
 
     storage
-        .store_credentials(account_name, &credentials_json)
+        .store_credentials(account_name, &credentials_json, !options.non_interactive)
         .context("Failed to store credentials")?;
 
     // Store account metadata
@@ -55,6 +57,9 @@ pub async fn login(storage: &crate::storage::SecureStorage, account_name: &str)
         email: None,
         created_at: chrono::Utc::now(),
         last_updated: chrono::Utc::now(),
+        budget: None,
+        base_url: options.base_url.clone(),
+        credentials_from: None,
     };
 
     storage
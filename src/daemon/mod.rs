@@ -0,0 +1,272 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::providers::QuotaInfo;
+
+/// How long clients wait for the daemon before fetching from the providers themselves
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Latest fetch result for one account, as served over the socket.
+///
+/// The quota is the provider's, without the account's budget, so clients apply the budget
+/// they have now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonAccount {
+    pub name: String,
+    pub provider: String,
+    /// When the quota was fetched, or the fetch failed
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota: Option<QuotaInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response to a `quotas` request
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DaemonResponse {
+    pub accounts: Vec<DaemonAccount>,
+}
+
+/// Ask a running daemon for its latest results and when it got them, keyed by account name.
+/// Returns `None` when no daemon is listening, so callers can fetch directly.
+pub async fn query_quotas(
+    socket: &Path,
+) -> Option<HashMap<String, (chrono::DateTime<chrono::Utc>, Result<QuotaInfo>)>> {
+    let response = match tokio::time::timeout(CLIENT_TIMEOUT, request(socket)).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            tracing::debug!("tokstat daemon not available: {:#}", e);
+            return None;
+        }
+        Err(_) => {
+            tracing::warn!("tokstat daemon did not answer in time");
+            return None;
+        }
+    };
+
+    Some(
+        response
+            .accounts
+            .into_iter()
+            .filter_map(|account| {
+                let result = match (account.quota, account.error) {
+                    (Some(quota), _) => Ok(quota),
+                    (None, Some(error)) => Err(anyhow::anyhow!(error)),
+                    (None, None) => return None,
+                };
+                Some((account.name, (account.fetched_at, result)))
+            })
+            .collect(),
+    )
+}
+
+#[cfg(unix)]
+async fn request(socket: &Path) -> Result<DaemonResponse> {
+    use anyhow::Context;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::UnixStream::connect(socket).await?;
+    stream.write_all(b"quotas\n").await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    serde_json::from_slice(&response).context("Invalid response from tokstat daemon")
+}
+
+#[cfg(not(unix))]
+async fn request(_socket: &Path) -> Result<DaemonResponse> {
+    anyhow::bail!("the tokstat daemon needs Unix domain sockets")
+}
+
+#[cfg(unix)]
+pub use server::run;
+
+#[cfg(not(unix))]
+pub async fn run(
+    _storage: crate::storage::SecureStorage,
+    _interval: Duration,
+    _fetch_options: crate::providers::FetchOptions,
+    _client: crate::http::HttpClient,
+    _config: &crate::config::Config,
+) -> Result<()> {
+    anyhow::bail!("tokstat daemon is only available on Unix-like systems")
+}
+
+#[cfg(unix)]
+mod server {
+    use anyhow::{Context, Result};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::signal::unix::{signal, SignalKind};
+    use tokio::sync::RwLock;
+
+    use super::{DaemonAccount, DaemonResponse};
+    use crate::alerts::Alerter;
+    use crate::config::Config;
+    use crate::http::HttpClient;
+    use crate::providers::FetchOptions;
+    use crate::storage::SecureStorage;
+
+    type DaemonState = Arc<RwLock<Vec<DaemonAccount>>>;
+
+    /// Poll every account each `interval` and serve the latest results on the daemon socket
+    pub async fn run(
+        storage: SecureStorage,
+        interval: Duration,
+        fetch_options: FetchOptions,
+        client: HttpClient,
+        config: &Config,
+    ) -> Result<()> {
+        let socket = storage.daemon_socket_path();
+        if UnixStream::connect(&socket).await.is_ok() {
+            anyhow::bail!(
+                "A tokstat daemon is already listening on {}",
+                socket.display()
+            );
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        let _ = std::fs::remove_file(&socket);
+
+        let listener = UnixListener::bind(&socket)
+            .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+        std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600))
+            .context("Failed to restrict access to the daemon socket")?;
+        println!(
+            "tokstat daemon listening on {}, polling every {}s",
+            socket.display(),
+            interval.as_secs()
+        );
+
+        let state: DaemonState = Arc::default();
+        let alerter = Alerter::new(&storage, config, &client);
+        tokio::spawn(poll_loop(
+            storage,
+            state.clone(),
+            interval,
+            fetch_options,
+            client,
+            alerter,
+        ));
+
+        // Stop cleanly under systemd and friends, not only on Ctrl+C
+        let mut terminate =
+            signal(SignalKind::terminate()).context("Failed to listen for termination signals")?;
+
+        let result = loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = match accepted {
+                        Ok(connection) => connection,
+                        Err(e) => break Err(e).context("Failed to accept connection"),
+                    };
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, state).await {
+                            tracing::debug!("daemon connection failed: {:#}", e);
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => break Ok(()),
+                _ = terminate.recv() => break Ok(()),
+            }
+        };
+
+        remove_socket(&socket);
+        result
+    }
+
+    fn remove_socket(socket: &Path) {
+        if let Err(e) = std::fs::remove_file(socket) {
+            tracing::warn!("Failed to remove {}: {}", socket.display(), e);
+        }
+    }
+
+    async fn poll_loop(
+        storage: SecureStorage,
+        state: DaemonState,
+        interval: Duration,
+        fetch_options: FetchOptions,
+        client: HttpClient,
+        alerter: Alerter,
+    ) {
+        loop {
+            // Reload accounts each round so logins and removals show up without a restart
+            match storage.list_accounts() {
+                Ok(accounts) => {
+                    let results = crate::providers::fetch_all_raw_quotas(
+                        &storage,
+                        &accounts,
+                        fetch_options,
+                        &client,
+                    )
+                    .await;
+                    // Alerts compare against the budgets, which are left out of what is served
+                    let budgeted: Vec<_> = accounts
+                        .iter()
+                        .zip(&results)
+                        .map(|(account, result)| match result {
+                            Ok(quota) => Ok(crate::providers::with_budget(quota.clone(), account)),
+                            Err(e) => Err(anyhow::anyhow!("{:#}", e)),
+                        })
+                        .collect();
+                    alerter.process(accounts.iter().zip(&budgeted));
+
+                    let now = chrono::Utc::now();
+                    let mut latest = Vec::with_capacity(accounts.len());
+                    for (account, result) in accounts.iter().zip(results) {
+                        let fetched_at = result.as_ref().map_or(now, |quota| quota.last_updated);
+                        let (quota, error) = match result {
+                            Ok(quota) => (Some(quota), None),
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to fetch quota for {}: {:#}",
+                                    account.name,
+                                    e
+                                );
+                                (None, Some(format!("{:#}", e)))
+                            }
+                        };
+                        latest.push(DaemonAccount {
+                            name: account.name.clone(),
+                            provider: account.provider.clone(),
+                            fetched_at,
+                            quota,
+                            error,
+                        });
+                    }
+                    *state.write().await = latest;
+                }
+                Err(e) => tracing::warn!("Failed to load accounts: {:#}", e),
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Each connection sends one request line and gets one JSON document back
+    async fn handle_connection(stream: UnixStream, state: DaemonState) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await?;
+
+        let body = match line.trim() {
+            "quotas" => serde_json::to_vec(&DaemonResponse {
+                accounts: state.read().await.clone(),
+            })?,
+            other => serde_json::to_vec(&serde_json::json!({
+                "error": format!("unknown request '{}'", other)
+            }))?,
+        };
+
+        writer.write_all(&body).await?;
+        writer.shutdown().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::{ForecastConfig, ForecastMethod};
+use crate::providers::QuotaInfo;
+use crate::storage::QuotaSnapshot;
+
+/// Predicted consumption of one limited metric
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricForecast {
+    /// `requests`, `tokens` or `cost`
+    pub metric: &'static str,
+    pub per_hour: f64,
+    pub per_day: f64,
+    pub remaining: f64,
+    /// When the limit is reached at the current pace, `None` if usage isn't growing
+    pub exhausted_at: Option<DateTime<Utc>>,
+    pub reset_date: Option<DateTime<Utc>>,
+    /// Whether the limit runs out before the quota resets
+    pub exhausts_before_reset: bool,
+    /// Share of the limit used by the reset date at the current pace, in percent
+    pub projected_percent_at_reset: Option<f64>,
+}
+
+impl MetricForecast {
+    /// One-line summary such as "at current pace: exhausted in 4d 3h, resets in 9d"
+    pub fn describe(&self, now: DateTime<Utc>) -> String {
+        let resets = self
+            .reset_date
+            .map(|reset| format!(", resets in {}", format_duration(reset - now)))
+            .unwrap_or_default();
+
+        match self.exhausted_at {
+            Some(exhausted) if exhausted <= now => {
+                format!("{} limit reached{}", self.metric, resets)
+            }
+            Some(exhausted) if self.exhausts_before_reset => format!(
+                "{} at current pace: exhausted in {}{}",
+                self.metric,
+                format_duration(exhausted - now),
+                resets
+            ),
+            _ => match self.projected_percent_at_reset {
+                Some(percent) => format!(
+                    "{} at current pace: {:.0}% used at reset{}",
+                    self.metric, percent, resets
+                ),
+                None => format!("{} at current pace: not exhausted{}", self.metric, resets),
+            },
+        }
+    }
+}
+
+/// Reads one metric from a snapshot
+type MetricValue = fn(&QuotaSnapshot) -> Option<f64>;
+
+/// Forecast every metric of `quota` that has a limit, using the account's snapshot history
+pub fn forecast(
+    history: &[QuotaSnapshot],
+    quota: &QuotaInfo,
+    config: &ForecastConfig,
+    now: DateTime<Utc>,
+) -> Vec<MetricForecast> {
+    let Some(limits) = &quota.limits else {
+        return Vec::new();
+    };
+
+    let metrics: [(&'static str, MetricValue, Option<f64>); 3] = [
+        (
+            "requests",
+            |s| s.requests_made.map(|v| v as f64),
+            limits.max_requests.map(|v| v as f64),
+        ),
+        (
+            "tokens",
+            |s| s.tokens_used.map(|v| v as f64),
+            limits.max_tokens.map(|v| v as f64),
+        ),
+        ("cost", |s| s.cost, limits.max_cost),
+    ];
+
+    let current = QuotaSnapshot::from_quota_info(quota);
+    let since = now - chrono::Duration::hours(config.lookback_hours as i64);
+
+    metrics
+        .into_iter()
+        .filter_map(|(metric, value_of, limit)| {
+            let limit = limit.filter(|limit| *limit > 0.0)?;
+            let used = value_of(&current)?;
+
+            let samples: Vec<(f64, f64)> = history
+                .iter()
+                .chain(std::iter::once(&current))
+                .filter(|snapshot| snapshot.timestamp >= since)
+                .filter_map(|snapshot| {
+                    let hours = (snapshot.timestamp - since).num_seconds() as f64 / 3600.0;
+                    value_of(snapshot).map(|value| (hours, value))
+                })
+                .collect();
+            let remaining = limit - used;
+            // A limit that is already used up is reported even without a rate to go by
+            let per_hour = match burn_rate(current_period(&samples), config.method) {
+                Some(per_hour) => per_hour,
+                None if remaining <= 0.0 => 0.0,
+                None => return None,
+            };
+
+            let exhausted_at = if remaining <= 0.0 {
+                Some(now)
+            } else if per_hour > 0.0 {
+                // Cap far-off dates so adding them to `now` can't overflow
+                const MAX_SECONDS: f64 = 100.0 * 365.0 * 86400.0;
+                let seconds = (remaining / per_hour * 3600.0).min(MAX_SECONDS);
+                Some(now + chrono::Duration::seconds(seconds as i64))
+            } else {
+                None
+            };
+            let projected_percent_at_reset = quota.reset_date.map(|reset| {
+                let hours_left = ((reset - now).num_seconds() as f64 / 3600.0).max(0.0);
+                (used + per_hour.max(0.0) * hours_left) / limit * 100.0
+            });
+
+            let exhausts_before_reset = match (exhausted_at, quota.reset_date) {
+                (Some(exhausted), Some(reset)) => exhausted < reset,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+
+            Some(MetricForecast {
+                metric,
+                per_hour,
+                per_day: per_hour * 24.0,
+                remaining,
+                exhausted_at,
+                reset_date: quota.reset_date,
+                exhausts_before_reset,
+                projected_percent_at_reset,
+            })
+        })
+        .collect()
+}
+
+/// Samples after the last drop in usage, i.e. since the quota last reset
+fn current_period(samples: &[(f64, f64)]) -> &[(f64, f64)] {
+    let start = samples
+        .windows(2)
+        .rposition(|pair| pair[1].1 < pair[0].1)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    &samples[start..]
+}
+
+/// Usage growth per hour, or `None` with fewer than two samples spread over time
+fn burn_rate(samples: &[(f64, f64)], method: ForecastMethod) -> Option<f64> {
+    let first = samples.first()?;
+    let last = samples.last()?;
+    if samples.len() < 2 || last.0 - first.0 <= 0.0 {
+        return None;
+    }
+
+    match method {
+        ForecastMethod::Linear => {
+            // Least-squares slope of usage over time
+            let n = samples.len() as f64;
+            let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+            let mean_y = samples.iter().map(|(_, y)| y).sum::<f64>() / n;
+            let (covariance, variance) = samples.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+                (
+                    cov + (x - mean_x) * (y - mean_y),
+                    var + (x - mean_x) * (x - mean_x),
+                )
+            });
+            (variance > 0.0).then(|| covariance / variance)
+        }
+        ForecastMethod::Ewma => {
+            // Exponentially weighted average of the rates between consecutive samples
+            const ALPHA: f64 = 0.3;
+            samples
+                .windows(2)
+                .filter(|pair| pair[1].0 > pair[0].0)
+                .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+                .fold(None, |average: Option<f64>, rate| {
+                    Some(match average {
+                        Some(average) => ALPHA * rate + (1.0 - ALPHA) * average,
+                        None => rate,
+                    })
+                })
+        }
+    }
+}
+
+/// Compact duration such as "4d 3h", "5h 12m" or "12m"
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{TokenLimits, TokenUsage};
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn quota(cost: f64, max_cost: f64, now: DateTime<Utc>) -> QuotaInfo {
+        QuotaInfo {
+            provider: "openrouter".to_string(),
+            account_name: "work".to_string(),
+            usage: TokenUsage {
+                tokens_used: None,
+                requests_made: None,
+                cost: Some(cost),
+            },
+            limits: Some(TokenLimits {
+                max_cost: Some(max_cost),
+                ..Default::default()
+            }),
+            reset_date: None,
+            last_updated: now,
+        }
+    }
+
+    #[test]
+    fn linear_burn_rate_is_least_squares_slope() {
+        let samples = [(0.0, 1.0), (1.0, 3.0), (2.0, 5.0), (3.0, 7.0)];
+        assert_eq!(burn_rate(&samples, ForecastMethod::Linear), Some(2.0));
+
+        let noisy = [(0.0, 0.0), (1.0, 2.0), (2.0, 2.0), (3.0, 6.0)];
+        assert_eq!(burn_rate(&noisy, ForecastMethod::Linear), Some(1.8));
+    }
+
+    #[test]
+    fn ewma_burn_rate_favours_recent_rates() {
+        // Rates between samples are 1, 1 and then 5 per hour
+        let samples = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 7.0)];
+        let rate = burn_rate(&samples, ForecastMethod::Ewma).unwrap();
+        assert!((rate - 2.2).abs() < 1e-9, "rate was {}", rate);
+    }
+
+    #[test]
+    fn burn_rate_needs_two_samples_spread_over_time() {
+        for method in [ForecastMethod::Linear, ForecastMethod::Ewma] {
+            assert_eq!(burn_rate(&[], method), None);
+            assert_eq!(burn_rate(&[(1.0, 5.0)], method), None);
+            assert_eq!(burn_rate(&[(1.0, 5.0), (1.0, 9.0)], method), None);
+        }
+    }
+
+    #[test]
+    fn current_period_starts_after_the_last_drop() {
+        let samples = [(0.0, 5.0), (1.0, 8.0), (2.0, 1.0), (3.0, 4.0), (4.0, 6.0)];
+        assert_eq!(current_period(&samples), &samples[2..]);
+
+        let growing = [(0.0, 1.0), (1.0, 1.0), (2.0, 3.0)];
+        assert_eq!(current_period(&growing), &growing[..]);
+        assert!(current_period(&[]).is_empty());
+    }
+
+    #[test]
+    fn forecast_reports_limit_reached_without_history() {
+        let now = at("2026-03-10T12:00:00Z");
+        let forecasts = forecast(
+            &[],
+            &quota(60.0, 50.0, now),
+            &ForecastConfig::default(),
+            now,
+        );
+
+        assert_eq!(forecasts.len(), 1);
+        assert_eq!(forecasts[0].metric, "cost");
+        assert_eq!(forecasts[0].exhausted_at, Some(now));
+        assert!(forecasts[0].exhausts_before_reset);
+        assert_eq!(forecasts[0].describe(now), "cost limit reached");
+    }
+
+    #[test]
+    fn forecast_needs_history_below_the_limit() {
+        let now = at("2026-03-10T12:00:00Z");
+        let forecasts = forecast(
+            &[],
+            &quota(10.0, 50.0, now),
+            &ForecastConfig::default(),
+            now,
+        );
+        assert!(forecasts.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use rand::Rng;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+use crate::config::HttpConfig;
+
+/// User-Agent sent when `http.user_agent` is empty
+pub const DEFAULT_USER_AGENT: &str = concat!("tokstat/", env!("CARGO_PKG_VERSION"));
+
+/// Extra root certificates in addition to `http.ca_certificates`, separated like `PATH`
+const CA_CERTIFICATES_ENV: &str = "TOKSTAT_CA_CERTIFICATES";
+
+/// Longer `Retry-After` waits are not honored, the response is returned instead
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The HTTP client shared by every provider and login flow.
+///
+/// Cloning is cheap and reuses the same connection pool.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl HttpClient {
+    /// Build the client from the `[http]` settings and `TOKSTAT_CA_CERTIFICATES`
+    pub fn new(config: &HttpConfig) -> Result<Self> {
+        let user_agent = if config.user_agent.is_empty() {
+            DEFAULT_USER_AGENT
+        } else {
+            config.user_agent.as_str()
+        };
+
+        let mut builder = reqwest::Client::builder()
+            .user_agent(user_agent)
+            .connect_timeout(Duration::from_secs(config.connect_timeout))
+            .read_timeout(Duration::from_secs(config.read_timeout));
+
+        // Without an explicit proxy reqwest uses HTTPS_PROXY, HTTP_PROXY and NO_PROXY
+        if !config.proxy.is_empty() {
+            let proxy = reqwest::Proxy::all(&config.proxy)
+                .with_context(|| format!("Invalid proxy URL '{}'", config.proxy))?
+                .no_proxy(reqwest::NoProxy::from_string(&config.no_proxy));
+            builder = builder.proxy(proxy);
+        }
+
+        let env_paths = std::env::var_os(CA_CERTIFICATES_ENV)
+            .map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let paths = config
+            .ca_certificates
+            .iter()
+            .map(std::path::PathBuf::from)
+            .chain(env_paths);
+        for path in paths {
+            let pem = std::fs::read(&path).with_context(|| {
+                format!("Failed to read CA certificates from {}", path.display())
+            })?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid PEM certificates in {}", path.display()))?;
+            if certificates.is_empty() {
+                anyhow::bail!("No PEM certificates found in {}", path.display());
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(Self {
+            client: builder.build().context("Failed to create HTTP client")?,
+            retries: config.retries,
+            backoff: Duration::from_millis(config.retry_backoff_ms),
+        })
+    }
+
+    pub fn get(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Send `request`, retrying rate limits, server errors and failed connections with
+    /// exponential backoff. A `Retry-After` header takes precedence over the backoff.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            // Requests with a streaming body can't be cloned and are only sent once
+            let retry = if attempt < self.retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let Some(retry) = retry else {
+                return request.send().await;
+            };
+
+            let delay = match retry.send().await {
+                Ok(response) if !is_retryable(response.status()) => return Ok(response),
+                Ok(response) => match retry_after(&response) {
+                    Some(delay) if delay > MAX_RETRY_AFTER => return Ok(response),
+                    Some(delay) => delay,
+                    None => self.backoff(attempt),
+                },
+                Err(e) if e.is_connect() || e.is_timeout() => self.backoff(attempt),
+                Err(e) => return Err(e),
+            };
+
+            attempt += 1;
+            tracing::debug!(
+                "Retrying request in {}ms (attempt {} of {})",
+                delay.as_millis(),
+                attempt,
+                self.retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// `backoff * 2^attempt`, plus up to a quarter of that so parallel fetches spread out
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self.backoff.saturating_mul(2u32.saturating_pow(attempt));
+        let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 4);
+        delay + Duration::from_millis(jitter)
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parse `Retry-After` as seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
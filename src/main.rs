@@ -1,12 +1,24 @@
+mod alerts;
 mod auth;
+mod config;
+mod daemon;
+mod forecast;
+mod format;
+mod http;
+mod metrics;
 mod providers;
+mod statusbar;
 mod storage;
+#[cfg(test)]
+mod testing;
 mod ui;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::builder::PossibleValuesParser;
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 use colored::*;
+use format::format_number;
 use std::io;
 use tracing::info;
 
@@ -23,6 +35,26 @@ struct Cli {
     #[arg(long = "json")]
     json: bool,
 
+    /// Maximum number of providers to query at the same time [default: fetch.concurrency]
+    #[arg(long, global = true)]
+    concurrency: Option<usize>,
+
+    /// Seconds to wait for each provider before giving up [default: fetch.timeout]
+    #[arg(long = "timeout", global = true)]
+    timeout_secs: Option<u64>,
+
+    /// Query the providers even if `tokstat daemon` is running
+    #[arg(long, global = true)]
+    no_daemon: bool,
+
+    /// Reuse cached quotas fetched within this time, e.g. 90s, 5m or 1h
+    #[arg(long, global = true, value_parser = parse_duration)]
+    max_age: Option<chrono::Duration>,
+
+    /// Only show cached quotas, without contacting any provider
+    #[arg(long, global = true)]
+    offline: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -31,13 +63,59 @@ struct Cli {
 enum Commands {
     /// Add and login to a new provider account
     Login {
-        /// Provider name (azure, copilot, openrouter)
-        #[arg(value_parser = ["azure", "copilot", "openrouter"])]
+        /// Provider name
+        #[arg(value_parser = PossibleValuesParser::new(providers::registry::ids()))]
         provider: String,
 
         /// Account name/alias
         #[arg(short, long)]
         name: Option<String>,
+
+        /// API base URL to use instead of the provider's, e.g. a gateway mirroring its API
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+
+        /// Read the API key from standard input instead of prompting for it
+        #[arg(long, conflicts_with_all = ["api_key_env", "github_token_env", "import_token"])]
+        api_key_stdin: bool,
+
+        /// Read the API key from this environment variable
+        #[arg(long, value_name = "VAR", conflicts_with_all = ["github_token_env", "import_token"])]
+        api_key_env: Option<String>,
+
+        /// Azure resource name, instead of AZURE_RESOURCE_NAME or a prompt
+        #[arg(long, value_name = "NAME")]
+        resource_name: Option<String>,
+
+        /// Read a GitHub token for Copilot from this environment variable instead of
+        /// running the device flow
+        #[arg(long, value_name = "VAR")]
+        github_token_env: Option<String>,
+
+        /// Reuse a GitHub token from GH_TOKEN, the Copilot editor plugin or the GitHub CLI
+        /// instead of running the device flow
+        #[arg(
+            long,
+            value_name = "SOURCE",
+            num_args = 0..=1,
+            default_missing_value = "auto",
+            conflicts_with = "github_token_env"
+        )]
+        import_token: Option<auth::copilot::TokenSource>,
+
+        /// Store the credentials without checking them against the provider
+        #[arg(long)]
+        no_validate: bool,
+
+        /// Never prompt, fail if anything required isn't given with a flag
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Run the GitHub device flow again for a Copilot account whose token was revoked
+    Reauth {
+        /// Account to re-authenticate
+        account: String,
     },
 
     /// List all configured accounts
@@ -58,10 +136,151 @@ enum Commands {
         name: Option<String>,
     },
 
+    /// Exit non-zero if any account is over a usage threshold or fails to fetch
+    Check {
+        /// Usage percentage that counts as over the limit [default: display.critical_threshold]
+        #[arg(long)]
+        threshold: Option<f64>,
+
+        /// Only check these accounts (can be repeated)
+        #[arg(short, long = "account")]
+        accounts: Vec<String>,
+    },
+
+    /// Run an HTTP server that exports quotas as Prometheus metrics
+    Serve {
+        /// Address to listen on, e.g. 127.0.0.1:9184
+        #[arg(long)]
+        metrics: std::net::SocketAddr,
+
+        /// Seconds between refreshes [default: dashboard.refresh_interval]
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+
+    /// Print a one-line summary for status bars and shell prompts
+    Status {
+        /// Output format
+        #[arg(long, value_enum)]
+        format: statusbar::StatusFormat,
+
+        /// Only show these accounts (can be repeated) [default: status.accounts]
+        #[arg(short, long = "account")]
+        accounts: Vec<String>,
+    },
+
+    /// Poll all accounts in the background and serve the results to other tokstat commands
+    Daemon {
+        /// Seconds between polls [default: daemon.interval]
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+
+    /// Set your own limits for an account
+    Budget {
+        #[command(subcommand)]
+        action: BudgetAction,
+    },
+
+    /// Work with alert targets from the [alerts] config section
+    Alerts {
+        #[command(subcommand)]
+        action: AlertsAction,
+    },
+
+    /// Manage where API keys and tokens are stored
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsAction,
+    },
+
+    /// View or change settings in config.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
     /// Show version information
     Version,
 }
 
+#[derive(Subcommand)]
+enum BudgetAction {
+    /// Set or update limits; limits that aren't given stay as they are
+    Set {
+        /// Account name
+        account: String,
+
+        /// Maximum number of tokens per billing period of the provider
+        #[arg(long)]
+        max_tokens: Option<u64>,
+
+        /// Maximum number of requests per billing period of the provider
+        #[arg(long)]
+        max_requests: Option<u64>,
+
+        /// Maximum cost in USD per billing period of the provider
+        #[arg(long)]
+        max_cost: Option<f64>,
+    },
+
+    /// Remove all manual limits from an account
+    Clear {
+        /// Account name
+        account: String,
+    },
+
+    /// Show the manual limits of one or all accounts
+    Show {
+        /// Account name (shows all if not specified)
+        account: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AlertsAction {
+    /// Send a sample alert to every configured target
+    Test,
+}
+
+#[derive(Subcommand)]
+enum CredentialsAction {
+    /// Move the credentials of all accounts into another backend
+    Migrate {
+        /// Backend to move the credentials to; they are read from the other one
+        #[arg(long, value_enum)]
+        to: storage::StoreKind,
+
+        /// Only migrate these accounts (can be repeated)
+        #[arg(short, long = "account")]
+        accounts: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a setting, or the whole config if no key is given
+    Get {
+        /// Dotted key, e.g. display.warn_threshold
+        key: Option<String>,
+    },
+
+    /// Change a setting
+    Set {
+        /// Dotted key, e.g. dashboard.refresh_interval
+        key: String,
+
+        /// New value
+        value: String,
+    },
+
+    /// Open config.toml in $VISUAL or $EDITOR
+    Edit,
+
+    /// Print the location of config.toml
+    Path,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
@@ -84,36 +303,110 @@ async fn main() -> Result<()> {
 
     // Handle commands (or fall back to status display)
     // Initialize storage
-    let storage = storage::SecureStorage::new()?;
+    let mut storage = storage::SecureStorage::new()?;
+
+    // Config commands must work even when the current file doesn't parse
+    if let Some(Commands::Config { action }) = &cli.command {
+        return run_config_command(&storage, action);
+    }
+
+    let config = config::Config::load(&storage.config_path())?;
+    storage.set_history_config(config.history.clone());
+    storage.set_credentials_config(&config.credentials);
+    storage.set_config_accounts(&config.accounts);
+    let fetch_options = providers::FetchOptions {
+        concurrency: cli.concurrency.unwrap_or(config.fetch.concurrency),
+        timeout: std::time::Duration::from_secs(cli.timeout_secs.unwrap_or(config.fetch.timeout)),
+        use_daemon: !cli.no_daemon,
+        max_age: cli.max_age,
+        offline: cli.offline,
+    };
+    // Commands that poll the providers themselves, or are asked to, must not read from the daemon
+    let direct_fetch_options = providers::FetchOptions {
+        use_daemon: false,
+        max_age: None,
+        offline: false,
+        ..fetch_options
+    };
+    let client = http::HttpClient::new(&config.http)?;
 
     match cli.command {
         Some(command) => match command {
-            Commands::Login { provider, name } => {
+            Commands::Login {
+                provider,
+                name,
+                base_url,
+                api_key_stdin,
+                api_key_env,
+                resource_name,
+                github_token_env,
+                import_token,
+                no_validate,
+                yes,
+            } => {
                 info!("Logging into {} provider", provider);
                 let account_name = name
                     .unwrap_or_else(|| format!("{}_{}", provider, chrono::Utc::now().timestamp()));
 
-                match provider.as_str() {
-                    "azure" => {
-                        auth::azure::login(&storage, &account_name).await?;
-                        println!(
-                            "✓ Successfully added Azure OpenAI account '{}'",
-                            account_name
-                        );
+                let info = providers::registry::find(&provider)
+                    .expect("clap only accepts registered providers");
+                if let Some(url) = &base_url {
+                    if !url.starts_with("http://") && !url.starts_with("https://") {
+                        anyhow::bail!("--base-url must start with http:// or https://");
                     }
-                    "copilot" => {
-                        auth::copilot::login(&storage, &account_name).await?;
-                        println!(
-                            "✓ Successfully logged into GitHub Copilot as '{}'",
-                            account_name
-                        );
+                }
+                let api_key = match (api_key_stdin, &api_key_env) {
+                    (true, _) => Some(auth::read_secret_stdin()?),
+                    (false, Some(var)) => Some(auth::read_secret_env(var)?),
+                    (false, None) => None,
+                };
+                let github_token = match (&github_token_env, import_token) {
+                    (Some(var), _) => Some(auth::read_secret_env(var)?),
+                    (None, Some(_)) if provider != "copilot" => {
+                        anyhow::bail!("--import-token only applies to copilot")
                     }
-                    "openrouter" => {
-                        auth::openrouter::login(&storage, &account_name).await?;
-                        println!("✓ Successfully added OpenRouter account '{}'", account_name);
+                    (None, Some(source)) => {
+                        let (token, origin) = auth::copilot::import_token(source)?;
+                        println!("Using the GitHub token from {}", origin);
+                        Some(token)
                     }
-                    _ => unreachable!("Invalid provider"),
+                    (None, None) => None,
+                };
+                let options = auth::LoginOptions {
+                    base_url,
+                    api_key,
+                    github_token,
+                    resource_name,
+                    no_validate,
+                    non_interactive: yes,
+                };
+                options.check(&provider)?;
+                (info.login)(&storage, &client, &account_name, &options).await?;
+                println!(
+                    "✓ Successfully added {} account '{}'",
+                    info.display_name, account_name
+                );
+            }
+
+            Commands::Reauth { account } => {
+                let account = storage.get_account(&account)?;
+                if account.credentials_from.is_some() {
+                    anyhow::bail!(
+                        "Account '{}' reads its token from config.toml, update it there",
+                        account.name
+                    );
                 }
+                if account.provider != "copilot" {
+                    anyhow::bail!(
+                        "Only Copilot accounts can be re-authenticated, run `tokstat login {} --name {}` to replace the key of '{}'",
+                        account.provider,
+                        account.name,
+                        account.name
+                    );
+                }
+                storage.unlock_credentials()?;
+                auth::copilot::reauth(&storage, &client, &account).await?;
+                println!("✓ Re-authenticated account '{}'", account.name);
             }
 
             Commands::List => {
@@ -125,7 +418,14 @@ async fn main() -> Result<()> {
                     println!("\nConfigured Accounts:");
                     println!("{}", "─".repeat(50));
                     for account in accounts {
-                        println!("  {} ({})", account.name, account.provider);
+                        let mut details = vec![account.provider.as_str()];
+                        if let Some(email) = &account.email {
+                            details.push(email);
+                        }
+                        if account.credentials_from.is_some() {
+                            details.push("config.toml");
+                        }
+                        println!("  {} ({})", account.name, details.join(", "));
                     }
                     println!();
                 }
@@ -133,47 +433,465 @@ async fn main() -> Result<()> {
 
             Commands::Dashboard => {
                 let accounts = storage.list_accounts()?;
-                ui::dashboard::run(storage, accounts).await?;
+                // The vault passphrase can't be asked for once the dashboard owns the terminal
+                storage.unlock_credentials()?;
+                ui::dashboard::run(storage, accounts, fetch_options, client, config).await?;
             }
 
             Commands::Remove { name } => {
+                storage.unlock_credentials()?;
                 storage.remove_account(&name)?;
                 println!("✓ Removed account '{}'", name);
             }
 
             Commands::Refresh { name } => {
-                if let Some(account_name) = name {
-                    println!("Refreshing quota for '{}'...", account_name);
-                    let account = storage.get_account(&account_name)?;
-                    let quota = providers::fetch_quota(&account).await?;
-                    println!("{:#?}", quota);
+                let accounts = match &name {
+                    Some(account_name) => {
+                        println!("Refreshing quota for '{}'...", account_name);
+                        vec![storage.get_account(account_name)?]
+                    }
+                    None => {
+                        println!("Refreshing all accounts...");
+                        storage.list_accounts()?
+                    }
+                };
+                let results =
+                    providers::fetch_all_quotas(&storage, &accounts, direct_fetch_options, &client)
+                        .await;
+                let deliveries = alerts::Alerter::new(&storage, &config, &client)
+                    .process(accounts.iter().zip(&results));
+
+                // A named account's fetch error is the command's error
+                let outcome = if name.is_some() {
+                    results
+                        .into_iter()
+                        .next()
+                        .expect("one result per account")
+                        .map(|quota| println!("{:#?}", quota))
                 } else {
-                    println!("Refreshing all accounts...");
-                    let accounts = storage.list_accounts()?;
-                    for account in accounts {
+                    for (account, result) in accounts.iter().zip(results) {
                         println!("\n{} ({}):", account.name, account.provider);
-                        match providers::fetch_quota(&account).await {
+                        match result {
                             Ok(quota) => println!("  {:#?}", quota),
                             Err(e) => println!("  Error: {}", e),
                         }
                     }
+                    Ok(())
+                };
+                for delivery in deliveries {
+                    let _ = delivery.await;
+                }
+                outcome?;
+            }
+
+            Commands::Check {
+                threshold,
+                accounts,
+            } => {
+                let threshold = threshold.unwrap_or(config.display.critical_threshold);
+                if !threshold.is_finite() || threshold < 0.0 {
+                    anyhow::bail!("--threshold must be a percentage of zero or more");
+                }
+                let code =
+                    match run_check(&storage, &accounts, threshold, fetch_options, &client).await {
+                        Ok(code) => code,
+                        Err(e) => {
+                            // Keep exit code 1 reserved for "over the threshold"
+                            eprintln!("Error: {:#}", e);
+                            CHECK_EXIT_FETCH_FAILED
+                        }
+                    };
+                std::process::exit(code);
+            }
+
+            Commands::Serve { metrics, interval } => {
+                let interval = interval.unwrap_or(config.dashboard.refresh_interval);
+                if interval == 0 {
+                    anyhow::bail!("--interval must be at least 1 second");
                 }
+                storage.unlock_credentials()?;
+                metrics::serve(
+                    storage,
+                    metrics,
+                    std::time::Duration::from_secs(interval),
+                    direct_fetch_options,
+                    client,
+                    &config,
+                )
+                .await?;
+            }
+
+            Commands::Status { format, accounts } => {
+                let accounts = statusbar::select_accounts(
+                    storage.list_accounts()?,
+                    &accounts,
+                    &config.status,
+                )?;
+                let results =
+                    providers::fetch_all_quotas(&storage, &accounts, fetch_options, &client).await;
+                println!(
+                    "{}",
+                    statusbar::render(format, &accounts, &results, &config.display, &config.status)
+                );
             }
 
+            Commands::Daemon { interval } => {
+                let interval = interval.unwrap_or(config.daemon.interval);
+                if interval == 0 {
+                    anyhow::bail!("--interval must be at least 1 second");
+                }
+                storage.unlock_credentials()?;
+                daemon::run(
+                    storage,
+                    std::time::Duration::from_secs(interval),
+                    direct_fetch_options,
+                    client,
+                    &config,
+                )
+                .await?;
+            }
+
+            Commands::Budget { action } => {
+                run_budget_command(&storage, action)?;
+            }
+
+            Commands::Alerts { action } => match action {
+                AlertsAction::Test => run_alert_test(&storage, &config, &client).await?,
+            },
+
+            Commands::Credentials { action } => match action {
+                CredentialsAction::Migrate { to, accounts } => {
+                    run_credentials_migrate(&storage, to, &accounts)?
+                }
+            },
+
+            Commands::Config { .. } => unreachable!("handled before loading the config"),
+
             Commands::Version => {
                 println!("tokstat {}", env!("CARGO_PKG_VERSION"));
             }
         },
         None => {
-            show_token_status(&storage, cli.json).await?;
+            let output = if cli.json {
+                config::OutputMode::Json
+            } else {
+                config.cli.output
+            };
+            show_token_status(&storage, output, fetch_options, &client, &config).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Exit code of `tokstat check` when an account is over the threshold
+const CHECK_EXIT_OVER_THRESHOLD: i32 = 1;
+/// Exit code of `tokstat check` when a fetch or the check itself failed, takes precedence
+/// over the threshold
+const CHECK_EXIT_FETCH_FAILED: i32 = 2;
+
+async fn run_check(
+    storage: &storage::SecureStorage,
+    only: &[String],
+    threshold: f64,
+    fetch_options: providers::FetchOptions,
+    client: &http::HttpClient,
+) -> Result<i32> {
+    let accounts = if only.is_empty() {
+        storage.list_accounts()?
+    } else {
+        only.iter()
+            .map(|name| storage.get_account(name))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    if accounts.is_empty() {
+        println!("No accounts configured. Use 'tokstat login' to add an account.");
+        return Ok(0);
+    }
+
+    let results = providers::fetch_all_quotas(storage, &accounts, fetch_options, client).await;
+
+    let mut over = 0;
+    let mut failed = 0;
+    for (account, result) in accounts.iter().zip(results) {
+        match result {
+            Ok(quota) => {
+                let offending: Vec<String> = quota
+                    .usage_percentages()
+                    .into_iter()
+                    .filter(|(_, percent)| *percent >= threshold)
+                    .map(|(metric, percent)| format!("{} {:.1}%", metric, percent))
+                    .collect();
+                if !offending.is_empty() {
+                    over += 1;
+                    println!(
+                        "✗ {} ({}): {}",
+                        account.name,
+                        account.provider,
+                        offending.join(", ")
+                    );
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!(
+                    "✗ {} ({}): fetch failed: {}",
+                    account.name, account.provider, e
+                );
+            }
+        }
+    }
+
+    if over == 0 && failed == 0 {
+        println!("✓ {} account(s) below {}% usage", accounts.len(), threshold);
+        return Ok(0);
+    }
+
+    println!(
+        "{} of {} account(s) at or above {}% usage, {} failed to fetch",
+        over,
+        accounts.len(),
+        threshold,
+        failed
+    );
+    Ok(if failed > 0 {
+        CHECK_EXIT_FETCH_FAILED
+    } else {
+        CHECK_EXIT_OVER_THRESHOLD
+    })
+}
+
+fn run_budget_command(storage: &storage::SecureStorage, action: BudgetAction) -> Result<()> {
+    match action {
+        BudgetAction::Set {
+            account,
+            max_tokens,
+            max_requests,
+            max_cost,
+        } => {
+            if max_tokens.is_none() && max_requests.is_none() && max_cost.is_none() {
+                anyhow::bail!("Nothing to set, pass --max-tokens, --max-requests or --max-cost");
+            }
+            if max_cost.is_some_and(|cost| cost <= 0.0) {
+                anyhow::bail!("--max-cost must be greater than zero");
+            }
+
+            let existing = storage.get_account(&account)?;
+            let mut budget = existing.budget.unwrap_or_default();
+            budget.limits = budget.limits.merged_with(&providers::TokenLimits {
+                max_tokens,
+                max_requests,
+                max_cost,
+            });
+
+            storage.set_budget(&account, Some(budget.clone()))?;
+            println!("✓ Budget for '{}': {}", account, budget.summary());
+        }
+        BudgetAction::Clear { account } => {
+            storage.set_budget(&account, None)?;
+            println!("✓ Removed budget from '{}'", account);
+        }
+        BudgetAction::Show { account } => {
+            let accounts = match account {
+                Some(name) => vec![storage.get_account(&name)?],
+                None => storage.list_accounts()?,
+            };
+            for account in accounts {
+                let budget = account
+                    .budget
+                    .as_ref()
+                    .map(storage::Budget::summary)
+                    .unwrap_or_else(|| "none".to_string());
+                println!("{}: {}", account.name, budget);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_credentials_migrate(
+    storage: &storage::SecureStorage,
+    to: storage::StoreKind,
+    only: &[String],
+) -> Result<()> {
+    let from = match to {
+        storage::StoreKind::Keyring => storage::StoreKind::Vault,
+        storage::StoreKind::Vault => storage::StoreKind::Keyring,
+    };
+    let accounts = storage
+        .list_accounts()?
+        .into_iter()
+        .filter(|account| only.is_empty() || only.contains(&account.name))
+        .collect::<Vec<_>>();
+    if let Some(missing) = only
+        .iter()
+        .find(|name| !accounts.iter().any(|account| account.name == **name))
+    {
+        anyhow::bail!("Account '{}' not found", missing);
+    }
+    // Accounts from config.toml read their credentials on every fetch, nothing is stored
+    let accounts = accounts
+        .into_iter()
+        .filter(|account| account.credentials_from.is_none())
+        .collect::<Vec<_>>();
+    if accounts.is_empty() {
+        println!("No accounts to migrate.");
+        return Ok(());
+    }
+
+    storage.unlock_vault()?;
+    println!(
+        "Moving credentials from the {} to the {}...",
+        from.as_str(),
+        to.as_str()
+    );
+    let mut failed = 0;
+    for account in &accounts {
+        match storage.migrate_credentials(&account.name, from, to) {
+            Ok(true) => println!("  {} {}", "✓".green(), account.name),
+            Ok(false) => println!(
+                "  {} {} (already in the {})",
+                "✓".green(),
+                account.name,
+                to.as_str()
+            ),
+            Err(e) => {
+                failed += 1;
+                println!("  {} {}: {:#}", "✗".red(), account.name, e);
+            }
+        }
+    }
+
+    if to == storage::StoreKind::Vault {
+        println!("\nVault: {}", storage.vault_path().display());
+    }
+    // Also covers `auto`, which keeps using a vault that still exists after moving out of it
+    if storage.default_credential_store() != to {
+        println!(
+            "Run `tokstat config set credentials.backend {}` so new logins are stored there too.",
+            to.as_str()
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} accounts could not be migrated",
+            failed,
+            accounts.len()
+        );
+    }
+    Ok(())
+}
+
+/// Deliver a sample alert to each target one by one and report what worked
+async fn run_alert_test(
+    storage: &storage::SecureStorage,
+    config: &config::Config,
+    client: &http::HttpClient,
+) -> Result<()> {
+    let alerter = alerts::Alerter::new(storage, config, client);
+    let targets = alerter.all_targets();
+    if targets.is_empty() {
+        println!("No alert targets configured. Set alerts.desktop, alerts.command or add [[alerts.webhooks]] to config.toml.");
+        return Ok(());
+    }
+
+    let alert = alerts::sample_alert();
+    let handles: Vec<_> = targets
+        .iter()
+        .cloned()
+        .map(|target| {
+            let (alert, display, client) = (alert.clone(), config.display.clone(), client.clone());
+            tokio::spawn(async move { target.deliver(&alert, &display, &client).await })
+        })
+        .collect();
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(
+            handle
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("delivery panicked"))),
+        );
+    }
+
+    let mut failed = 0;
+    for (target, result) in targets.iter().zip(results) {
+        match result {
+            Ok(()) => println!("✓ {}", target.describe()),
+            Err(e) => {
+                failed += 1;
+                println!("✗ {}: {:#}", target.describe(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} alert targets failed", failed, targets.len());
+    }
+    Ok(())
+}
+
+fn run_config_command(storage: &storage::SecureStorage, action: &ConfigAction) -> Result<()> {
+    let path = storage.config_path();
+
+    match action {
+        ConfigAction::Get { key } => {
+            let config = config::Config::load(&path)?;
+            match key {
+                Some(key) => match config.get(key)? {
+                    toml::Value::String(value) => println!("{}", value),
+                    toml::Value::Table(table) => print!("{}", toml::to_string_pretty(&table)?),
+                    value => println!("{}", value),
+                },
+                None => print!("{}", toml::to_string_pretty(&config)?),
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            let mut config = config::Config::load(&path)?;
+            config.set(key, value)?;
+            config.save(&path)?;
+            println!("✓ Set {} = {}", key, config.get(key)?);
+        }
+        ConfigAction::Edit => {
+            if !path.exists() {
+                config::Config::default().save(&path)?;
+            }
+
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_string());
+            let status = std::process::Command::new(&editor)
+                .arg(&path)
+                .status()
+                .with_context(|| format!("Failed to launch editor '{}'", editor))?;
+            if !status.success() {
+                anyhow::bail!("Editor '{}' exited with {}", editor, status);
+            }
+
+            // Report problems right away instead of on the next run
+            let content = std::fs::read_to_string(&path).context("Failed to read config file")?;
+            config::Config::parse(&content)?;
+            println!("✓ Config saved to {}", path.display());
+        }
+        ConfigAction::Path => {
+            println!("{}", path.display());
         }
     }
 
     Ok(())
 }
 
-async fn show_token_status(storage: &storage::SecureStorage, json_output: bool) -> Result<()> {
+async fn show_token_status(
+    storage: &storage::SecureStorage,
+    output: config::OutputMode,
+    fetch_options: providers::FetchOptions,
+    client: &http::HttpClient,
+    config: &config::Config,
+) -> Result<()> {
     let accounts = storage.list_accounts()?;
+    let json_output = output == config::OutputMode::Json;
 
     if accounts.is_empty() {
         if json_output {
@@ -190,13 +908,19 @@ async fn show_token_status(storage: &storage::SecureStorage, json_output: bool)
         return Ok(());
     }
 
+    let started = chrono::Utc::now();
+    let results = providers::fetch_all_quotas(storage, &accounts, fetch_options, client).await;
+    // Deliver while the output is printed, only waiting for it before exiting
+    let deliveries =
+        alerts::Alerter::new(storage, config, client).process(accounts.iter().zip(&results));
+
     if json_output {
         // JSON output
         let mut json_accounts = Vec::new();
-        for account in accounts {
-            let quota_result = providers::fetch_quota(&account).await;
+        for (account, quota_result) in accounts.into_iter().zip(results) {
             let account_json = match quota_result {
                 Ok(quota) => {
+                    let forecasts = forecast_quota(storage, &quota, config);
                     serde_json::json!({
                         "name": account.name,
                         "provider": account.provider,
@@ -213,7 +937,8 @@ async fn show_token_status(storage: &storage::SecureStorage, json_output: bool)
                             })
                         }),
                         "reset_date": quota.reset_date.map(|dt| dt.to_rfc3339()),
-                        "last_updated": quota.last_updated.to_rfc3339()
+                        "last_updated": quota.last_updated.to_rfc3339(),
+                        "forecast": forecasts
                     })
                 }
                 Err(err) => {
@@ -227,30 +952,44 @@ async fn show_token_status(storage: &storage::SecureStorage, json_output: bool)
             json_accounts.push(account_json);
         }
         println!("{}", serde_json::to_string_pretty(&json_accounts)?);
-    } else if atty::is(atty::Stream::Stdout) {
+    } else if output == config::OutputMode::Fancy
+        || (output == config::OutputMode::Auto && atty::is(atty::Stream::Stdout))
+    {
         // Fancy CLI output with colors and box drawing (default)
-        render_status_fancy_cli(&accounts, storage).await?;
+        render_status_fancy_cli(&accounts, results, started, storage, config)?;
     } else {
         // Plain text fallback when piping
-        render_status_text_only(&accounts, storage).await?;
+        render_status_text_only(&accounts, results, started, storage, config)?;
     }
 
+    for delivery in deliveries {
+        let _ = delivery.await;
+    }
     Ok(())
 }
 
-async fn render_status_text_only(
+/// Forecast the limits of a quota from the account's history
+fn forecast_quota(
+    storage: &storage::SecureStorage,
+    quota: &providers::QuotaInfo,
+    config: &config::Config,
+) -> Vec<forecast::MetricForecast> {
+    let history = storage
+        .get_quota_history(&quota.account_name)
+        .unwrap_or_default();
+    forecast::forecast(&history, quota, &config.forecast, chrono::Utc::now())
+}
+
+fn render_status_text_only(
     accounts: &[crate::storage::Account],
-    _storage: &storage::SecureStorage,
+    results: Vec<Result<providers::QuotaInfo>>,
+    started: chrono::DateTime<chrono::Utc>,
+    storage: &storage::SecureStorage,
+    config: &config::Config,
 ) -> Result<()> {
-    // Fetch all quotas first
-    let mut account_data: Vec<(
-        &crate::storage::Account,
-        anyhow::Result<crate::providers::QuotaInfo>,
-    )> = Vec::new();
-    for account in accounts {
-        let quota_result = crate::providers::fetch_quota(account).await;
-        account_data.push((account, quota_result));
-    }
+    let display = &config.display;
+
+    let account_data = accounts.iter().zip(results);
 
     println!("Token Status");
     println!("{}", "=".repeat(60));
@@ -303,8 +1042,20 @@ async fn render_status_text_only(
                     }
                 }
 
-                println!("  Reset: {}", format_datetime(quota.reset_date));
-                println!("  Updated: {}", format_datetime(Some(quota.last_updated)));
+                let now = chrono::Utc::now();
+                for forecast in forecast_quota(storage, &quota, config) {
+                    println!("  Forecast: {}", forecast.describe(now));
+                }
+
+                println!("  Reset: {}", format_datetime(quota.reset_date, display));
+                let cached = cached_note(&quota, started)
+                    .map(|note| format!(" ({})", note))
+                    .unwrap_or_default();
+                println!(
+                    "  Updated: {}{}",
+                    format_datetime(Some(quota.last_updated), display),
+                    cached
+                );
             }
             Err(err) => {
                 println!("  Error: {}", err);
@@ -315,21 +1066,18 @@ async fn render_status_text_only(
     Ok(())
 }
 
-async fn render_status_fancy_cli(
+fn render_status_fancy_cli(
     accounts: &[crate::storage::Account],
-    _storage: &storage::SecureStorage,
+    results: Vec<Result<providers::QuotaInfo>>,
+    started: chrono::DateTime<chrono::Utc>,
+    storage: &storage::SecureStorage,
+    config: &config::Config,
 ) -> Result<()> {
+    let display = &config.display;
+
     use colored::*;
 
-    // Fetch all quotas first
-    let mut account_data: Vec<(
-        &crate::storage::Account,
-        anyhow::Result<crate::providers::QuotaInfo>,
-    )> = Vec::new();
-    for account in accounts {
-        let quota_result = crate::providers::fetch_quota(account).await;
-        account_data.push((account, quota_result));
-    }
+    let account_data = accounts.iter().zip(results);
 
     // Box dimensions
     const BOX_WIDTH: usize = 80;
@@ -352,12 +1100,7 @@ async fn render_status_fancy_cli(
     );
 
     for (account, quota_result) in account_data {
-        let provider_emoji = match account.provider.as_str() {
-            "azure" => "☁️",
-            "copilot" => "🤖",
-            "openrouter" => "🌐",
-            _ => "🔌",
-        };
+        let provider_emoji = providers::registry::icon(&account.provider);
 
         // Account header with box drawing - fully enclosed
         println!(
@@ -394,7 +1137,7 @@ async fn render_status_fancy_cli(
             Ok(quota) => {
                 // Requests with visual bar
                 if let Some(requests) = quota.usage.requests_made {
-                    let requests_info = format_requests_with_bar(&quota, requests);
+                    let requests_info = format_requests_with_bar(&quota, requests, display);
                     let line = format!(
                         "{}{}{}",
                         "│".bright_magenta(),
@@ -406,7 +1149,7 @@ async fn render_status_fancy_cli(
 
                 // Tokens with visual bar
                 if let Some(tokens) = quota.usage.tokens_used {
-                    let tokens_info = format_tokens_with_bar(&quota, tokens);
+                    let tokens_info = format_tokens_with_bar(&quota, tokens, display);
                     let line = format!(
                         "{}{}{}",
                         "│".bright_magenta(),
@@ -418,7 +1161,7 @@ async fn render_status_fancy_cli(
 
                 // Cost with visual bar
                 if let Some(cost) = quota.usage.cost {
-                    let cost_info = format_cost_with_bar(&quota, cost);
+                    let cost_info = format_cost_with_bar(&quota, cost, display);
                     let line = format!(
                         "{}{}{}",
                         "│".bright_magenta(),
@@ -428,8 +1171,25 @@ async fn render_status_fancy_cli(
                     println!("{}", line);
                 }
 
+                // Forecast per limited metric
+                let now = chrono::Utc::now();
+                for forecast in forecast_quota(storage, &quota, config) {
+                    let text = forecast.describe(now);
+                    let forecast_line = if forecast.exhausts_before_reset {
+                        format!("  {} {}", "📉", text.red())
+                    } else {
+                        format!("  {} {}", "📈".dimmed(), text.dimmed())
+                    };
+                    println!(
+                        "{}{}{}",
+                        "│".bright_magenta(),
+                        pad_to_width(&forecast_line, BOX_WIDTH),
+                        "│".bright_magenta()
+                    );
+                }
+
                 // Reset date
-                let reset_text = format_datetime(quota.reset_date);
+                let reset_text = format_datetime(quota.reset_date, display);
                 let reset_line = format!(
                     "  {} {}",
                     "🔄".dimmed(),
@@ -443,12 +1203,20 @@ async fn render_status_fancy_cli(
                 );
 
                 // Last updated
-                let updated_text = format_datetime(Some(quota.last_updated));
-                let updated_line = format!(
-                    "  {} {}",
-                    "⏱️".dimmed(),
-                    format!("Updated: {}", updated_text).dimmed()
-                );
+                let updated_text = format_datetime(Some(quota.last_updated), display);
+                let updated_line = match cached_note(&quota, started) {
+                    Some(note) => format!(
+                        "  {} {} {}",
+                        "⏳".yellow(),
+                        format!("Updated: {}", updated_text).dimmed(),
+                        format!("({})", note).yellow()
+                    ),
+                    None => format!(
+                        "  {} {}",
+                        "⏱️".dimmed(),
+                        format!("Updated: {}", updated_text).dimmed()
+                    ),
+                };
                 println!(
                     "{}{}{}",
                     "│".bright_magenta(),
@@ -484,7 +1252,11 @@ async fn render_status_fancy_cli(
     Ok(())
 }
 
-fn format_requests_with_bar(quota: &crate::providers::QuotaInfo, requests: u64) -> String {
+fn format_requests_with_bar(
+    quota: &crate::providers::QuotaInfo,
+    requests: u64,
+    display: &config::DisplayConfig,
+) -> String {
     use colored::*;
 
     if let Some(max_requests) = quota.limits.as_ref().and_then(|limits| limits.max_requests) {
@@ -495,17 +1267,16 @@ fn format_requests_with_bar(quota: &crate::providers::QuotaInfo, requests: u64)
             0.0
         };
 
-        let bar_width = 20;
-        let filled = (percent_used / 100.0 * bar_width as f64) as usize;
+        let bar_width = display.bar_width;
+        let filled = ((percent_used / 100.0 * bar_width as f64) as usize).min(bar_width);
         let empty = bar_width - filled;
 
-        let (color_fn, icon): (fn(&str) -> ColoredString, &str) = if percent_used < 50.0 {
-            (|s: &str| s.green(), "✓")
-        } else if percent_used < 80.0 {
-            (|s: &str| s.yellow(), "⚠")
-        } else {
-            (|s: &str| s.red(), "✗")
-        };
+        let (color_fn, icon): (fn(&str) -> ColoredString, &str) =
+            match display.usage_level(percent_used) {
+                config::UsageLevel::Normal => (|s: &str| s.green(), "✓"),
+                config::UsageLevel::Warning => (|s: &str| s.yellow(), "⚠"),
+                config::UsageLevel::Critical => (|s: &str| s.red(), "✗"),
+            };
 
         let bar = format!("{}{}", "█".repeat(filled), "░".repeat(empty));
 
@@ -530,7 +1301,11 @@ fn format_requests_with_bar(quota: &crate::providers::QuotaInfo, requests: u64)
     }
 }
 
-fn format_tokens_with_bar(quota: &crate::providers::QuotaInfo, tokens: u64) -> String {
+fn format_tokens_with_bar(
+    quota: &crate::providers::QuotaInfo,
+    tokens: u64,
+    display: &config::DisplayConfig,
+) -> String {
     use colored::*;
 
     if let Some(max_tokens) = quota.limits.as_ref().and_then(|limits| limits.max_tokens) {
@@ -540,17 +1315,16 @@ fn format_tokens_with_bar(quota: &crate::providers::QuotaInfo, tokens: u64) -> S
             0.0
         };
 
-        let bar_width = 20;
-        let filled = (percent_used / 100.0 * bar_width as f64) as usize;
+        let bar_width = display.bar_width;
+        let filled = ((percent_used / 100.0 * bar_width as f64) as usize).min(bar_width);
         let empty = bar_width - filled;
 
-        let (color_fn, icon): (fn(&str) -> ColoredString, &str) = if percent_used < 50.0 {
-            (|s: &str| s.green(), "✓")
-        } else if percent_used < 80.0 {
-            (|s: &str| s.yellow(), "⚠")
-        } else {
-            (|s: &str| s.red(), "✗")
-        };
+        let (color_fn, icon): (fn(&str) -> ColoredString, &str) =
+            match display.usage_level(percent_used) {
+                config::UsageLevel::Normal => (|s: &str| s.green(), "✓"),
+                config::UsageLevel::Warning => (|s: &str| s.yellow(), "⚠"),
+                config::UsageLevel::Critical => (|s: &str| s.red(), "✗"),
+            };
 
         let bar = format!("{}{}", "█".repeat(filled), "░".repeat(empty));
 
@@ -574,7 +1348,11 @@ fn format_tokens_with_bar(quota: &crate::providers::QuotaInfo, tokens: u64) -> S
     }
 }
 
-fn format_cost_with_bar(quota: &crate::providers::QuotaInfo, cost: f64) -> String {
+fn format_cost_with_bar(
+    quota: &crate::providers::QuotaInfo,
+    cost: f64,
+    display: &config::DisplayConfig,
+) -> String {
     use colored::*;
 
     if let Some(max_cost) = quota.limits.as_ref().and_then(|limits| limits.max_cost) {
@@ -584,17 +1362,16 @@ fn format_cost_with_bar(quota: &crate::providers::QuotaInfo, cost: f64) -> Strin
             0.0
         };
 
-        let bar_width = 20;
-        let filled = (percent_used / 100.0 * bar_width as f64) as usize;
+        let bar_width = display.bar_width;
+        let filled = ((percent_used / 100.0 * bar_width as f64) as usize).min(bar_width);
         let empty = bar_width - filled;
 
-        let (color_fn, icon): (fn(&str) -> ColoredString, &str) = if percent_used < 50.0 {
-            (|s: &str| s.green(), "✓")
-        } else if percent_used < 80.0 {
-            (|s: &str| s.yellow(), "⚠")
-        } else {
-            (|s: &str| s.red(), "✗")
-        };
+        let (color_fn, icon): (fn(&str) -> ColoredString, &str) =
+            match display.usage_level(percent_used) {
+                config::UsageLevel::Normal => (|s: &str| s.green(), "✓"),
+                config::UsageLevel::Warning => (|s: &str| s.yellow(), "⚠"),
+                config::UsageLevel::Critical => (|s: &str| s.red(), "✗"),
+            };
 
         let bar = format!("{}{}", "█".repeat(filled), "░".repeat(empty));
 
@@ -649,17 +1426,49 @@ fn strip_ansi_codes(text: &str) -> String {
     result
 }
 
-fn format_datetime(dt: Option<chrono::DateTime<chrono::Utc>>) -> String {
-    dt.map(|value| value.format("%Y-%m-%d %H:%M UTC").to_string())
+fn format_datetime(
+    dt: Option<chrono::DateTime<chrono::Utc>>,
+    display: &config::DisplayConfig,
+) -> String {
+    dt.map(|value| display.format_datetime(value))
         .unwrap_or_else(|| "Unknown".to_string())
 }
 
-fn format_number(n: u64) -> String {
-    if n >= 1_000_000 {
-        format!("{:.1}M", n as f64 / 1_000_000.0)
-    } else if n >= 1_000 {
-        format!("{:.1}K", n as f64 / 1_000.0)
-    } else {
-        n.to_string()
-    }
+/// "cached 12m ago" for quotas fetched before this run, i.e. taken from the cache or daemon
+fn cached_note(
+    quota: &providers::QuotaInfo,
+    started: chrono::DateTime<chrono::Utc>,
+) -> Option<String> {
+    (quota.last_updated < started).then(|| {
+        format!(
+            "cached {} ago",
+            forecast::format_duration(started - quota.last_updated)
+        )
+    })
+}
+
+/// Parse durations such as `90`, `90s`, `5m`, `1h` or `2d`
+fn parse_duration(value: &str) -> std::result::Result<chrono::Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration like 90s, 5m or 1h", value))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}', use s, m, h or d", unit)),
+    };
+    // Cache entries are compared against now minus the duration, which has to be a valid time
+    number
+        .checked_mul(seconds)
+        .and_then(|seconds| i64::try_from(seconds).ok())
+        .and_then(chrono::Duration::try_seconds)
+        .filter(|duration| chrono::Utc::now().checked_sub_signed(*duration).is_some())
+        .ok_or_else(|| format!("'{}' is too long", value))
 }
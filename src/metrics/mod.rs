@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+
+use crate::alerts::Alerter;
+use crate::config::Config;
+use crate::http::HttpClient;
+use crate::providers::{FetchOptions, QuotaInfo};
+use crate::storage::SecureStorage;
+
+/// What the exporter knows about one account between refreshes
+#[derive(Default)]
+struct AccountMetrics {
+    provider: String,
+    quota: Option<QuotaInfo>,
+    last_success: Option<chrono::DateTime<chrono::Utc>>,
+    last_fetch_ok: bool,
+    fetch_errors: u64,
+}
+
+type MetricsState = Arc<RwLock<Vec<(String, AccountMetrics)>>>;
+
+/// Serve Prometheus metrics on `addr`, refreshing all accounts every `interval`
+pub async fn serve(
+    storage: SecureStorage,
+    addr: SocketAddr,
+    interval: Duration,
+    fetch_options: FetchOptions,
+    client: HttpClient,
+    config: &Config,
+) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    println!("Serving metrics on http://{}/metrics", addr);
+
+    let state: MetricsState = Arc::default();
+    let alerter = Alerter::new(&storage, config, &client);
+    tokio::spawn(refresh_loop(
+        storage,
+        state.clone(),
+        interval,
+        fetch_options,
+        client,
+        alerter,
+    ));
+
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .context("Failed to accept connection")?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, state).await {
+                tracing::debug!("metrics connection failed: {:#}", e);
+            }
+        });
+    }
+}
+
+async fn refresh_loop(
+    storage: SecureStorage,
+    state: MetricsState,
+    interval: Duration,
+    fetch_options: FetchOptions,
+    client: HttpClient,
+    alerter: Alerter,
+) {
+    loop {
+        // Reload accounts each round so logins and removals show up without a restart
+        match storage.list_accounts() {
+            Ok(accounts) => {
+                let results =
+                    crate::providers::fetch_all_quotas(&storage, &accounts, fetch_options, &client)
+                        .await;
+                let now = chrono::Utc::now();
+                alerter.process(accounts.iter().zip(&results));
+
+                let mut state = state.write().await;
+                let mut previous: Vec<(String, AccountMetrics)> = state.drain(..).collect();
+                for (account, result) in accounts.iter().zip(results) {
+                    let mut metrics = previous
+                        .iter()
+                        .position(|(name, _)| *name == account.name)
+                        .map(|i| previous.swap_remove(i).1)
+                        .unwrap_or_default();
+                    metrics.provider = account.provider.clone();
+
+                    match result {
+                        Ok(quota) => {
+                            metrics.quota = Some(quota);
+                            metrics.last_success = Some(now);
+                            metrics.last_fetch_ok = true;
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to fetch quota for {}: {:#}", account.name, e);
+                            metrics.last_fetch_ok = false;
+                            metrics.fetch_errors += 1;
+                        }
+                    }
+                    state.push((account.name.clone(), metrics));
+                }
+            }
+            Err(e) => tracing::warn!("Failed to load accounts: {:#}", e),
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: MetricsState) -> Result<()> {
+    // Only the request line matters, so read until the end of the headers and ignore the rest
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("");
+    let path = request_line.next().unwrap_or("");
+    let path = path.split('?').next().unwrap_or("");
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let state = state.read().await;
+            (
+                "200 OK",
+                "text/plain; version=0.0.4; charset=utf-8",
+                render(&state, chrono::Utc::now()),
+            )
+        }
+        ("GET", "/") => (
+            "200 OK",
+            "text/plain; charset=utf-8",
+            "tokstat metrics exporter, see /metrics\n".to_string(),
+        ),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "Not found\n".to_string(),
+        ),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// One metric family: name, type, help text and how to read the value for an account
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&AccountMetrics, chrono::DateTime<chrono::Utc>) -> Option<f64>,
+}
+
+const FAMILIES: &[Family] = &[
+    Family {
+        name: "tokstat_tokens_used",
+        kind: "gauge",
+        help: "Tokens used in the current period",
+        value: |m, _| m.quota.as_ref()?.usage.tokens_used.map(|v| v as f64),
+    },
+    Family {
+        name: "tokstat_requests_made",
+        kind: "gauge",
+        help: "Requests made in the current period",
+        value: |m, _| m.quota.as_ref()?.usage.requests_made.map(|v| v as f64),
+    },
+    Family {
+        name: "tokstat_cost_usd",
+        kind: "gauge",
+        help: "Cost in USD for the current period",
+        value: |m, _| m.quota.as_ref()?.usage.cost,
+    },
+    Family {
+        name: "tokstat_max_tokens",
+        kind: "gauge",
+        help: "Token limit, including manual budgets",
+        value: |m, _| {
+            m.quota
+                .as_ref()?
+                .limits
+                .as_ref()?
+                .max_tokens
+                .map(|v| v as f64)
+        },
+    },
+    Family {
+        name: "tokstat_max_requests",
+        kind: "gauge",
+        help: "Request limit, including manual budgets",
+        value: |m, _| {
+            m.quota
+                .as_ref()?
+                .limits
+                .as_ref()?
+                .max_requests
+                .map(|v| v as f64)
+        },
+    },
+    Family {
+        name: "tokstat_max_cost_usd",
+        kind: "gauge",
+        help: "Cost limit in USD, including manual budgets",
+        value: |m, _| m.quota.as_ref()?.limits.as_ref()?.max_cost,
+    },
+    Family {
+        name: "tokstat_reset_seconds",
+        kind: "gauge",
+        help: "Seconds until the quota resets",
+        value: |m, now| {
+            let reset = m.quota.as_ref()?.reset_date?;
+            Some((reset - now).num_seconds().max(0) as f64)
+        },
+    },
+    Family {
+        name: "tokstat_last_success_timestamp_seconds",
+        kind: "gauge",
+        help: "Unix time of the last successful fetch",
+        value: |m, _| m.last_success.map(|t| t.timestamp() as f64),
+    },
+    Family {
+        name: "tokstat_up",
+        kind: "gauge",
+        help: "Whether the last fetch succeeded (1) or failed (0)",
+        value: |m, _| Some(if m.last_fetch_ok { 1.0 } else { 0.0 }),
+    },
+    Family {
+        name: "tokstat_fetch_errors_total",
+        kind: "counter",
+        help: "Failed fetches since the exporter started",
+        value: |m, _| Some(m.fetch_errors as f64),
+    },
+];
+
+/// Render all accounts in the Prometheus text exposition format
+fn render(state: &[(String, AccountMetrics)], now: chrono::DateTime<chrono::Utc>) -> String {
+    let mut out = String::new();
+    for family in FAMILIES {
+        let _ = writeln!(out, "# HELP {} {}", family.name, family.help);
+        let _ = writeln!(out, "# TYPE {} {}", family.name, family.kind);
+        for (account, metrics) in state {
+            if let Some(value) = (family.value)(metrics, now) {
+                let _ = writeln!(
+                    out,
+                    "{}{{account=\"{}\",provider=\"{}\"}} {}",
+                    family.name,
+                    escape_label(account),
+                    escape_label(&metrics.provider),
+                    value
+                );
+            }
+        }
+    }
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
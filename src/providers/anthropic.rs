@@ -0,0 +1,231 @@
+use super::{Provider, QuotaInfo, TokenUsage};
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnthropicCredentials {
+    /// Admin API key (`sk-ant-admin...`), regular API keys cannot read usage reports
+    pub api_key: String,
+}
+
+pub struct AnthropicProvider {
+    base_url: String,
+}
+
+impl AnthropicProvider {
+    /// Create a provider using `ANTHROPIC_BASE_URL` if set, otherwise the public API
+    pub fn new() -> Self {
+        match std::env::var("ANTHROPIC_BASE_URL") {
+            Ok(url) if !url.is_empty() => Self::with_base_url(url),
+            _ => Self::with_base_url(DEFAULT_BASE_URL),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Fetch every page of an Admin API report and return the flattened buckets
+    async fn fetch_report<T: DeserializeOwned>(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut buckets = Vec::new();
+        let mut page: Option<String> = None;
+
+        loop {
+            let mut request = client
+                .get(&url)
+                .header("x-api-key", api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .query(query);
+            if let Some(page) = &page {
+                request = request.query(&[("page", page)]);
+            }
+
+            let response = client
+                .send(request)
+                .await
+                .context("Failed to connect to Anthropic Admin API")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                if status == 401 || status == 403 {
+                    anyhow::bail!(
+                        "Access denied to Anthropic Admin API ({}). An Admin API key (sk-ant-admin...) is required.",
+                        status
+                    );
+                }
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Failed to fetch Anthropic report: {} - {}", status, body);
+            }
+
+            let report: AnthropicReport<T> = response
+                .json()
+                .await
+                .context("Failed to parse Anthropic report response")?;
+
+            buckets.extend(report.data);
+
+            match report.next_page {
+                Some(next) if report.has_more => page = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(buckets)
+    }
+}
+
+impl Default for AnthropicProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for AnthropicProvider {
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo> {
+        let creds: AnthropicCredentials =
+            serde_json::from_str(credentials).context("Failed to parse Anthropic credentials")?;
+
+        let now = chrono::Utc::now();
+        let (period_start, reset_date) = super::current_month_period(now);
+        let starting_at = period_start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let ending_at = now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+        // Token usage for the current billing period, one bucket per day
+        let usage_buckets: Vec<AnthropicUsageBucket> = self
+            .fetch_report(
+                client,
+                &creds.api_key,
+                "/v1/organizations/usage_report/messages",
+                &[
+                    ("starting_at", starting_at.clone()),
+                    ("ending_at", ending_at.clone()),
+                    ("bucket_width", "1d".to_string()),
+                    ("limit", "31".to_string()),
+                ],
+            )
+            .await?;
+
+        let tokens_used: u64 = usage_buckets
+            .iter()
+            .flat_map(|bucket| bucket.results.iter())
+            .map(AnthropicUsageResult::total_tokens)
+            .sum();
+
+        // Cost report amounts are decimal strings in cents
+        let cost_buckets: Vec<AnthropicCostBucket> = self
+            .fetch_report(
+                client,
+                &creds.api_key,
+                "/v1/organizations/cost_report",
+                &[
+                    ("starting_at", starting_at),
+                    ("ending_at", ending_at),
+                    ("limit", "31".to_string()),
+                ],
+            )
+            .await?;
+
+        let cost_cents: f64 = cost_buckets
+            .iter()
+            .flat_map(|bucket| bucket.results.iter())
+            .filter_map(|result| result.amount.parse::<f64>().ok())
+            .sum();
+
+        Ok(QuotaInfo {
+            provider: "anthropic".to_string(),
+            account_name: "".to_string(), // Will be filled by caller
+            usage: TokenUsage {
+                tokens_used: Some(tokens_used),
+                requests_made: None, // Usage reports don't include request counts
+                cost: Some(cost_cents / 100.0),
+            },
+            limits: None,
+            reset_date: Some(reset_date),
+            last_updated: now,
+        })
+    }
+
+    fn provider_name(&self) -> &str {
+        "anthropic"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicReport<T> {
+    data: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsageBucket {
+    #[serde(default)]
+    results: Vec<AnthropicUsageResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicUsageResult {
+    #[serde(default)]
+    uncached_input_tokens: u64,
+    #[serde(default)]
+    cache_creation: Option<AnthropicCacheCreation>,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+impl AnthropicUsageResult {
+    fn total_tokens(&self) -> u64 {
+        let cache_creation = self
+            .cache_creation
+            .as_ref()
+            .map(|c| c.ephemeral_1h_input_tokens + c.ephemeral_5m_input_tokens)
+            .unwrap_or(0);
+
+        self.uncached_input_tokens
+            + cache_creation
+            + self.cache_read_input_tokens
+            + self.output_tokens
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicCacheCreation {
+    #[serde(default)]
+    ephemeral_1h_input_tokens: u64,
+    #[serde(default)]
+    ephemeral_5m_input_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicCostBucket {
+    #[serde(default)]
+    results: Vec<AnthropicCostResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicCostResult {
+    amount: String,
+}
@@ -1,4 +1,5 @@
 use super::{Provider, QuotaInfo, TokenLimits, TokenUsage};
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
@@ -8,34 +9,67 @@ pub struct AzureCredentials {
     pub resource_name: String,
 }
 
-pub struct AzureProvider;
+pub struct AzureProvider {
+    /// Replaces `https://{resource}.openai.azure.com`, e.g. for a gateway in front of Azure
+    base_url: Option<String>,
+}
 
-#[async_trait::async_trait]
-impl Provider for AzureProvider {
-    async fn fetch_quota(&self, credentials: &str) -> Result<QuotaInfo> {
-        let creds: AzureCredentials =
-            serde_json::from_str(credentials).context("Failed to parse Azure credentials")?;
+impl AzureProvider {
+    /// Create a provider using `AZURE_OPENAI_ENDPOINT` if set, otherwise the resource's endpoint
+    pub fn new() -> Self {
+        match std::env::var("AZURE_OPENAI_ENDPOINT") {
+            Ok(url) if !url.is_empty() => Self::with_base_url(url),
+            _ => Self { base_url: None },
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into().trim_end_matches('/').to_string()),
+        }
+    }
+
+    pub fn base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    /// The base URL override, or the public endpoint of `resource_name`
+    pub fn endpoint(&self, resource_name: &str) -> Result<String> {
+        if let Some(base_url) = &self.base_url {
+            return Ok(base_url.clone());
+        }
 
-        let resource_name = if creds.resource_name.is_empty() {
+        let resource_name = if resource_name.is_empty() {
             std::env::var("AZURE_RESOURCE_NAME").context(
                 "AZURE_RESOURCE_NAME env var not set and no resource name in credentials",
             )?
         } else {
-            creds.resource_name.clone()
+            resource_name.to_string()
         };
+        Ok(format!("https://{}.openai.azure.com", resource_name))
+    }
+}
 
-        let client = reqwest::Client::new();
+impl Default for AzureProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for AzureProvider {
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo> {
+        let creds: AzureCredentials =
+            serde_json::from_str(credentials).context("Failed to parse Azure credentials")?;
+
+        let endpoint = self.endpoint(&creds.resource_name)?;
 
         // Fetch models via the data-plane API to verify credentials and gather info
-        let models_url = format!(
-            "https://{}.openai.azure.com/openai/models?api-version=2024-06-01",
-            resource_name
-        );
+        let models_url = format!("{}/openai/models?api-version=2024-06-01", endpoint);
 
+        let request = client.get(&models_url).header("api-key", &creds.api_key);
         let response = client
-            .get(&models_url)
-            .header("api-key", &creds.api_key)
-            .send()
+            .send(request)
             .await
             .context("Failed to connect to Azure OpenAI")?;
 
@@ -54,17 +88,10 @@ impl Provider for AzureProvider {
         let _ = models.data.len();
 
         // Try to get token usage from the usage endpoint (may not be available)
-        let usage_url = format!(
-            "https://{}.openai.azure.com/openai/usage?api-version=2024-06-01",
-            resource_name
-        );
-
-        let (tokens_used, requests_made) = match client
-            .get(&usage_url)
-            .header("api-key", &creds.api_key)
-            .send()
-            .await
-        {
+        let usage_url = format!("{}/openai/usage?api-version=2024-06-01", endpoint);
+
+        let usage_request = client.get(&usage_url).header("api-key", &creds.api_key);
+        let (tokens_used, requests_made) = match client.send(usage_request).await {
             Ok(resp) if resp.status().is_success() => {
                 match resp.json::<AzureUsageResponse>().await {
                     Ok(usage) => {
@@ -79,17 +106,12 @@ impl Provider for AzureProvider {
         };
 
         // Try to list deployments for rate limit info (may not be available on all API versions)
-        let deployments_url = format!(
-            "https://{}.openai.azure.com/openai/deployments?api-version=2022-12-01",
-            resource_name
-        );
+        let deployments_url = format!("{}/openai/deployments?api-version=2022-12-01", endpoint);
 
-        let (total_tpm, total_rpm) = match client
+        let deployments_request = client
             .get(&deployments_url)
-            .header("api-key", &creds.api_key)
-            .send()
-            .await
-        {
+            .header("api-key", &creds.api_key);
+        let (total_tpm, total_rpm) = match client.send(deployments_request).await {
             Ok(resp) if resp.status().is_success() => {
                 match resp.json::<AzureDeploymentsResponse>().await {
                     Ok(deployments) => {
@@ -1,16 +1,91 @@
 use super::{Provider, QuotaInfo, TokenLimits, TokenUsage};
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// GitHub OAuth tokens don't expire, they stay valid until they are revoked.
+/// Credentials stored by older versions also hold an unused `refresh_token` and
+/// `expires_at`, which are ignored.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CopilotCredentials {
     pub access_token: String,
-    pub refresh_token: String,
-    pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-pub struct CopilotProvider;
+/// The user a GitHub token belongs to
+#[derive(Debug, Deserialize)]
+pub struct GitHubUser {
+    pub login: String,
+    /// Public email, only set if the user made one visible on their profile
+    pub email: Option<String>,
+}
+
+impl GitHubUser {
+    /// `login <email>`, as stored in `Account.email`
+    pub fn describe(&self) -> String {
+        match &self.email {
+            Some(email) if !email.is_empty() => format!("{} <{}>", self.login, email),
+            _ => self.login.clone(),
+        }
+    }
+}
+
+pub const DEFAULT_BASE_URL: &str = "https://api.github.com";
+
+pub struct CopilotProvider {
+    base_url: String,
+}
+
+impl CopilotProvider {
+    /// Create a provider using `COPILOT_BASE_URL` if set, otherwise the public GitHub API
+    pub fn new() -> Self {
+        match std::env::var("COPILOT_BASE_URL") {
+            Ok(url) if !url.is_empty() => Self::with_base_url(url),
+            _ => Self::with_base_url(DEFAULT_BASE_URL),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Look up the user the token belongs to, which also checks that it is still valid
+    pub async fn fetch_user(&self, client: &HttpClient, access_token: &str) -> Result<GitHubUser> {
+        let request = client
+            .get(format!("{}/user", self.base_url))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("Accept", "application/vnd.github+json");
+        let response = client
+            .send(request)
+            .await
+            .context("Failed to fetch GitHub user")?;
+
+        let status = response.status();
+        if status == 401 {
+            anyhow::bail!(REVOKED_MESSAGE);
+        }
+        if !status.is_success() {
+            anyhow::bail!("Failed to fetch GitHub user: {}", status);
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse GitHub user response")
+    }
+}
+
+/// GitHub answers 401 once a token was revoked, e.g. in the user's authorized apps
+const REVOKED_MESSAGE: &str =
+    "GitHub token was revoked or is no longer valid (401), run `tokstat reauth` for this account";
+
+impl Default for CopilotProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 // NOTE: GitHub does not provide a public API for individual Copilot usage data.
 // The endpoint used below is an internal endpoint that may not be accessible
@@ -20,28 +95,16 @@ pub struct CopilotProvider;
 // 3. GitHub CLI (gh copilot usage) - which may use internal endpoints
 #[async_trait::async_trait]
 impl Provider for CopilotProvider {
-    async fn fetch_quota(&self, credentials: &str) -> Result<QuotaInfo> {
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo> {
         let creds: CopilotCredentials =
             serde_json::from_str(credentials).context("Failed to parse Copilot credentials")?;
 
-        // Check if token is expired
-        let now = chrono::Utc::now();
-        let access_token = if now >= creds.expires_at {
-            // Token expired, need to refresh
-            // For now, return error - refresh logic would go here
-            anyhow::bail!("Access token expired, please login again");
-        } else {
-            creds.access_token.clone()
-        };
-
-        let client = reqwest::Client::new();
-
         // Fetch usage data from GitHub Copilot API
+        let request = client
+            .get(format!("{}/copilot_internal/user", self.base_url))
+            .header("Authorization", format!("Bearer {}", creds.access_token));
         let response = client
-            .get("https://api.github.com/copilot_internal/user")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .header("User-Agent", "ai-quota-monitor")
-            .send()
+            .send(request)
             .await
             .context("Failed to fetch Copilot usage")?;
 
@@ -53,7 +116,9 @@ impl Provider for CopilotProvider {
                      for individual Copilot usage. Please check your usage at: \
                      https://github.com/settings/copilot"
                 );
-            } else if status == 401 || status == 403 {
+            } else if status == 401 {
+                anyhow::bail!(REVOKED_MESSAGE);
+            } else if status == 403 {
                 anyhow::bail!(
                     "Access denied to Copilot usage API ({}). Your token may not have the required permissions.",
                     status
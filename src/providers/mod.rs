@@ -1,9 +1,20 @@
+pub mod anthropic;
 pub mod azure;
 pub mod copilot;
+pub mod openai;
 pub mod openrouter;
+pub mod registry;
+mod secret;
 
-use anyhow::Result;
+#[cfg(test)]
+mod tests;
+
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuotaInfo {
@@ -15,6 +26,60 @@ pub struct QuotaInfo {
     pub last_updated: chrono::DateTime<chrono::Utc>,
 }
 
+impl QuotaInfo {
+    /// Percentage of each limit used so far, for every metric that has both usage and a limit
+    pub fn usage_percentages(&self) -> Vec<(&'static str, f64)> {
+        self.usage_against_limits()
+            .into_iter()
+            .map(|(metric, used, limit)| (metric, used / limit * 100.0))
+            .collect()
+    }
+
+    /// Usage and limit of every metric that has both, skipping zero limits
+    pub fn usage_against_limits(&self) -> Vec<(&'static str, f64, f64)> {
+        let Some(limits) = &self.limits else {
+            return Vec::new();
+        };
+
+        let mut metrics = Vec::new();
+        if let (Some(used), Some(max)) = (self.usage.requests_made, limits.max_requests) {
+            if max > 0 {
+                metrics.push(("requests", used as f64, max as f64));
+            }
+        }
+        if let (Some(used), Some(max)) = (self.usage.tokens_used, limits.max_tokens) {
+            if max > 0 {
+                metrics.push(("tokens", used as f64, max as f64));
+            }
+        }
+        if let (Some(used), Some(max)) = (self.usage.cost, limits.max_cost) {
+            if max > 0.0 {
+                metrics.push(("cost", used, max));
+            }
+        }
+        metrics
+    }
+}
+
+/// Result for an account with `--offline` and nothing cached. Nothing was fetched, so it is
+/// not a fetch failure either, e.g. for alerts.
+#[derive(Debug)]
+pub struct NotCached {
+    pub account_name: String,
+}
+
+impl std::fmt::Display for NotCached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No cached quota for '{}' yet, run tokstat once without --offline",
+            self.account_name
+        )
+    }
+}
+
+impl std::error::Error for NotCached {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub tokens_used: Option<u64>,
@@ -22,30 +87,324 @@ pub struct TokenUsage {
     pub cost: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TokenLimits {
     pub max_tokens: Option<u64>,
     pub max_requests: Option<u64>,
     pub max_cost: Option<f64>,
 }
 
+impl TokenLimits {
+    /// Combine with `overrides`, preferring every limit that `overrides` sets
+    pub fn merged_with(&self, overrides: &TokenLimits) -> TokenLimits {
+        TokenLimits {
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            max_requests: overrides.max_requests.or(self.max_requests),
+            max_cost: overrides.max_cost.or(self.max_cost),
+        }
+    }
+}
+
+/// Merge an account's manual budget over the limits reported by its provider
+pub fn apply_budget(quota: &mut QuotaInfo, budget: &crate::storage::Budget) {
+    if budget.is_empty() {
+        return;
+    }
+
+    let provider_limits = quota.limits.take().unwrap_or_default();
+    quota.limits = Some(provider_limits.merged_with(&budget.limits));
+}
+
 #[async_trait::async_trait]
 pub trait Provider: Send + Sync {
-    async fn fetch_quota(&self, credentials: &str) -> Result<QuotaInfo>;
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo>;
     #[allow(dead_code)]
     fn provider_name(&self) -> &str;
 }
 
-pub async fn fetch_quota(account: &crate::storage::Account) -> Result<QuotaInfo> {
-    let storage = crate::storage::SecureStorage::new()?;
-    let credentials = storage.get_credentials(&account.name)?;
+/// Query the account's provider, returning its quota without the account's budget applied
+pub async fn fetch_quota(
+    storage: &crate::storage::SecureStorage,
+    account: &crate::storage::Account,
+    client: &HttpClient,
+) -> Result<QuotaInfo> {
+    let info = registry::find(&account.provider)
+        .with_context(|| format!("Unknown provider: {}", account.provider))?;
+
+    let credentials = match &account.credentials_from {
+        Some(source) => secret::resolve_credentials(source, info).await?,
+        None => storage.get_credentials(&account.name)?,
+    };
+    let provider = (info.create)(account.base_url.as_deref());
+
+    provider.fetch_quota(client, &credentials).await
+}
+
+/// Controls how many providers are queried at once and how long each may take
+#[derive(Debug, Clone, Copy)]
+pub struct FetchOptions {
+    pub concurrency: usize,
+    pub timeout: Duration,
+    /// Use the results of a running `tokstat daemon` instead of querying the providers
+    pub use_daemon: bool,
+    /// Reuse cached quotas fetched at most this long ago
+    pub max_age: Option<chrono::Duration>,
+    /// Only use cached quotas, never query the providers
+    pub offline: bool,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 8,
+            timeout: Duration::from_secs(30),
+            use_daemon: true,
+            max_age: None,
+            offline: false,
+        }
+    }
+}
+
+/// Fetch a single account's quota, failing if the provider doesn't answer within `timeout`
+pub async fn fetch_quota_with_timeout(
+    storage: &crate::storage::SecureStorage,
+    account: &crate::storage::Account,
+    client: &HttpClient,
+    timeout: Duration,
+) -> Result<QuotaInfo> {
+    match tokio::time::timeout(timeout, fetch_quota(storage, account, client)).await {
+        Ok(result) => result,
+        Err(_) => anyhow::bail!(
+            "Timed out after {}s waiting for {}",
+            timeout.as_secs(),
+            account.provider
+        ),
+    }
+}
+
+/// Start fetching quotas for all accounts in the background.
+///
+/// Each result is sent as soon as its provider answers, tagged with the account's index.
+/// The channel closes once every fetch has finished. Cached quotas within `options.max_age`
+/// and the results of a running daemon are used first; only the remaining accounts are
+/// fetched directly. With `options.offline` nothing is fetched at all.
+pub fn spawn_quota_fetches(
+    storage: crate::storage::SecureStorage,
+    accounts: Vec<crate::storage::Account>,
+    options: FetchOptions,
+    client: HttpClient,
+) -> mpsc::UnboundedReceiver<(usize, Result<QuotaInfo>)> {
+    let budgeted = accounts.clone();
+    let mut raw = spawn_raw_quota_fetches(storage, accounts, options, client);
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some((index, result)) = raw.recv().await {
+            let result = result.map(|quota| with_budget(quota, &budgeted[index]));
+            if tx.send((index, result)).is_err() {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+/// Like `spawn_quota_fetches`, but the quotas are sent as the providers reported them,
+/// without the accounts' budgets
+pub fn spawn_raw_quota_fetches(
+    storage: crate::storage::SecureStorage,
+    accounts: Vec<crate::storage::Account>,
+    options: FetchOptions,
+    client: HttpClient,
+) -> mpsc::UnboundedReceiver<(usize, Result<QuotaInfo>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut pending: Vec<(usize, crate::storage::Account)> =
+        accounts.into_iter().enumerate().collect();
+
+    tokio::spawn(async move {
+        let oldest = options.max_age.map(|max_age| chrono::Utc::now() - max_age);
+
+        if options.offline || options.max_age.is_some() {
+            let mut cached = storage.cached_quotas().unwrap_or_else(|e| {
+                tracing::warn!("Failed to read cached quotas: {:#}", e);
+                Default::default()
+            });
+
+            pending.retain(|(index, account)| match cached.remove(&account.name) {
+                Some(entry)
+                    if options.offline
+                        || oldest.is_some_and(|oldest| entry.fetched_at >= oldest) =>
+                {
+                    let _ = tx.send((*index, Ok(entry.quota)));
+                    false
+                }
+                _ => true,
+            });
+
+            if options.offline {
+                for (index, account) in pending {
+                    let _ = tx.send((
+                        index,
+                        Err(NotCached {
+                            account_name: account.name,
+                        }
+                        .into()),
+                    ));
+                }
+                return;
+            }
+        }
+
+        if options.use_daemon && !pending.is_empty() {
+            let mut cached = crate::daemon::query_quotas(&storage.daemon_socket_path())
+                .await
+                .unwrap_or_default();
+
+            // With --max-age, results the daemon got longer ago are fetched again
+            pending.retain(|(index, account)| match cached.remove(&account.name) {
+                Some((fetched_at, result)) if oldest.is_none_or(|oldest| fetched_at >= oldest) => {
+                    let _ = tx.send((*index, result));
+                    false
+                }
+                _ => true,
+            });
+        }
+
+        // Only now is it known whether stored credentials are needed at all; ask for the
+        // vault passphrase on a blocking thread, before any fetch could wait on the vault
+        if pending
+            .iter()
+            .any(|(_, account)| account.credentials_from.is_none())
+        {
+            let unlocking = storage.clone();
+            let unlocked =
+                match tokio::task::spawn_blocking(move || unlocking.unlock_credentials()).await {
+                    Ok(result) => result,
+                    Err(e) => Err(e.into()),
+                };
+            if let Err(e) = unlocked {
+                pending.retain(|(index, account)| {
+                    if account.credentials_from.is_some() {
+                        return true;
+                    }
+                    let _ = tx.send((*index, Err(anyhow::anyhow!("{:#}", e))));
+                    false
+                });
+            }
+        }
+
+        spawn_direct_fetches(storage, pending, options, client, tx);
+    });
+
+    rx
+}
+
+/// Merge the account's current budget over a quota as reported by its provider.
+///
+/// The cache and the daemon keep quotas without budgets, so a budget that was changed or
+/// cleared since applies to them as well.
+pub fn with_budget(mut quota: QuotaInfo, account: &crate::storage::Account) -> QuotaInfo {
+    if let Some(budget) = &account.budget {
+        apply_budget(&mut quota, budget);
+    }
+    quota
+}
+
+/// Query each provider, at most `options.concurrency` at a time
+fn spawn_direct_fetches(
+    storage: crate::storage::SecureStorage,
+    accounts: Vec<(usize, crate::storage::Account)>,
+    options: FetchOptions,
+    client: HttpClient,
+    tx: mpsc::UnboundedSender<(usize, Result<QuotaInfo>)>,
+) {
+    let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+
+    for (index, account) in accounts {
+        let semaphore = Arc::clone(&semaphore);
+        let tx = tx.clone();
+        let client = client.clone();
+        let storage = storage.clone();
+        tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = fetch_quota_with_timeout(&storage, &account, &client, options.timeout)
+                .await
+                .map(|mut quota| {
+                    quota.account_name = account.name.clone();
+                    quota
+                });
+            // Only fresh results go into the cache and history, never ones read back from them
+            if let Ok(quota) = &result {
+                if let Err(e) = storage.cache_quota(quota) {
+                    tracing::warn!("Failed to cache quota for {}: {:#}", account.name, e);
+                }
+                if let Err(e) = storage.add_quota_snapshot(&account.name, quota) {
+                    tracing::warn!("Failed to record quota snapshot: {:#}", e);
+                }
+            }
+            // The receiver may already be gone, e.g. when the dashboard quit mid-refresh
+            let _ = tx.send((index, result));
+        });
+    }
+}
+
+/// Fetch quotas for all accounts concurrently, returning results in account order
+pub async fn fetch_all_quotas(
+    storage: &crate::storage::SecureStorage,
+    accounts: &[crate::storage::Account],
+    options: FetchOptions,
+    client: &HttpClient,
+) -> Vec<Result<QuotaInfo>> {
+    let rx = spawn_quota_fetches(storage.clone(), accounts.to_vec(), options, client.clone());
+    collect_results(rx, accounts).await
+}
+
+/// Like `fetch_all_quotas`, but without the accounts' budgets
+pub async fn fetch_all_raw_quotas(
+    storage: &crate::storage::SecureStorage,
+    accounts: &[crate::storage::Account],
+    options: FetchOptions,
+    client: &HttpClient,
+) -> Vec<Result<QuotaInfo>> {
+    let rx = spawn_raw_quota_fetches(storage.clone(), accounts.to_vec(), options, client.clone());
+    collect_results(rx, accounts).await
+}
+
+async fn collect_results(
+    mut rx: mpsc::UnboundedReceiver<(usize, Result<QuotaInfo>)>,
+    accounts: &[crate::storage::Account],
+) -> Vec<Result<QuotaInfo>> {
+    let mut results: Vec<Option<Result<QuotaInfo>>> = accounts.iter().map(|_| None).collect();
+    while let Some((index, result)) = rx.recv().await {
+        results[index] = Some(result);
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("Quota fetch task failed"))))
+        .collect()
+}
+
+/// Return the start of the calendar month containing `now` and the start of the next one
+pub fn current_month_period(
+    now: chrono::DateTime<chrono::Utc>,
+) -> (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>) {
+    use chrono::{Datelike, TimeZone};
 
-    let provider: Box<dyn Provider> = match account.provider.as_str() {
-        "azure" => Box::new(azure::AzureProvider),
-        "copilot" => Box::new(copilot::CopilotProvider),
-        "openrouter" => Box::new(openrouter::OpenRouterProvider),
-        _ => anyhow::bail!("Unknown provider: {}", account.provider),
+    let start = chrono::Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+    let (next_year, next_month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
     };
+    let next = chrono::Utc
+        .with_ymd_and_hms(next_year, next_month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
 
-    provider.fetch_quota(&credentials).await
+    (start, next)
 }
@@ -0,0 +1,201 @@
+use super::{Provider, QuotaInfo, TokenUsage};
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenAICredentials {
+    /// Admin key (`sk-admin-...`), project keys cannot read organization usage
+    pub api_key: String,
+}
+
+pub struct OpenAIProvider {
+    base_url: String,
+}
+
+impl OpenAIProvider {
+    /// Create a provider using `OPENAI_BASE_URL` if set, otherwise the public API
+    pub fn new() -> Self {
+        match std::env::var("OPENAI_BASE_URL") {
+            Ok(url) if !url.is_empty() => Self::with_base_url(url),
+            _ => Self::with_base_url(DEFAULT_BASE_URL),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Fetch every page of an organization report and return the flattened buckets
+    async fn fetch_buckets<T: DeserializeOwned>(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>> {
+        let url = format!("{}{}", self.base_url, path);
+        let mut buckets = Vec::new();
+        let mut page: Option<String> = None;
+
+        loop {
+            let mut request = client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .query(query);
+            if let Some(page) = &page {
+                request = request.query(&[("page", page)]);
+            }
+
+            let response = client
+                .send(request)
+                .await
+                .context("Failed to connect to OpenAI API")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                if status == 401 || status == 403 {
+                    anyhow::bail!(
+                        "Access denied to OpenAI organization API ({}). An admin key (sk-admin-...) is required.",
+                        status
+                    );
+                }
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Failed to fetch OpenAI usage: {} - {}", status, body);
+            }
+
+            let page_data: OpenAIPage<T> = response
+                .json()
+                .await
+                .context("Failed to parse OpenAI usage response")?;
+
+            buckets.extend(page_data.data);
+
+            match page_data.next_page {
+                Some(next) if page_data.has_more => page = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(buckets)
+    }
+}
+
+impl Default for OpenAIProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl Provider for OpenAIProvider {
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo> {
+        let creds: OpenAICredentials =
+            serde_json::from_str(credentials).context("Failed to parse OpenAI credentials")?;
+
+        let now = chrono::Utc::now();
+        let (period_start, reset_date) = super::current_month_period(now);
+        let query = [
+            ("start_time", period_start.timestamp().to_string()),
+            ("end_time", now.timestamp().to_string()),
+            ("bucket_width", "1d".to_string()),
+            ("limit", "31".to_string()),
+        ];
+
+        // Completions usage covers chat/responses traffic, which is where tokens are billed
+        let usage_buckets: Vec<OpenAIBucket<OpenAIUsageResult>> = self
+            .fetch_buckets(
+                client,
+                &creds.api_key,
+                "/v1/organization/usage/completions",
+                &query,
+            )
+            .await?;
+
+        let usage_results = usage_buckets
+            .iter()
+            .flat_map(|bucket| bucket.results.iter());
+        let (tokens_used, requests_made) =
+            usage_results.fold((0u64, 0u64), |(tokens, requests), result| {
+                (
+                    tokens + result.input_tokens + result.output_tokens,
+                    requests + result.num_model_requests,
+                )
+            });
+
+        let cost_buckets: Vec<OpenAIBucket<OpenAICostResult>> = self
+            .fetch_buckets(client, &creds.api_key, "/v1/organization/costs", &query)
+            .await?;
+
+        let cost: f64 = cost_buckets
+            .iter()
+            .flat_map(|bucket| bucket.results.iter())
+            .filter_map(|result| result.amount.as_ref())
+            .map(|amount| amount.value)
+            .sum();
+
+        Ok(QuotaInfo {
+            provider: "openai".to_string(),
+            account_name: "".to_string(), // Will be filled by caller
+            usage: TokenUsage {
+                tokens_used: Some(tokens_used),
+                requests_made: Some(requests_made),
+                cost: Some(cost),
+            },
+            // OpenAI doesn't expose spend limits through the API, set a budget instead
+            limits: None,
+            reset_date: Some(reset_date),
+            last_updated: now,
+        })
+    }
+
+    fn provider_name(&self) -> &str {
+        "openai"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIPage<T> {
+    data: Vec<T>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    next_page: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIBucket<T> {
+    #[serde(default = "Vec::new")]
+    results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIUsageResult {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    num_model_requests: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAICostResult {
+    #[serde(default)]
+    amount: Option<OpenAICostAmount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAICostAmount {
+    #[serde(default)]
+    value: f64,
+}
@@ -1,4 +1,5 @@
 use super::{Provider, QuotaInfo, TokenLimits, TokenUsage};
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
@@ -7,21 +8,50 @@ pub struct OpenRouterCredentials {
     pub api_key: String,
 }
 
-pub struct OpenRouterProvider;
+pub const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+pub struct OpenRouterProvider {
+    base_url: String,
+}
+
+impl OpenRouterProvider {
+    /// Create a provider using `OPENROUTER_BASE_URL` if set, otherwise the public API
+    pub fn new() -> Self {
+        match std::env::var("OPENROUTER_BASE_URL") {
+            Ok(url) if !url.is_empty() => Self::with_base_url(url),
+            _ => Self::with_base_url(DEFAULT_BASE_URL),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+impl Default for OpenRouterProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[async_trait::async_trait]
 impl Provider for OpenRouterProvider {
-    async fn fetch_quota(&self, credentials: &str) -> Result<QuotaInfo> {
+    async fn fetch_quota(&self, client: &HttpClient, credentials: &str) -> Result<QuotaInfo> {
         let creds: OpenRouterCredentials =
             serde_json::from_str(credentials).context("Failed to parse OpenRouter credentials")?;
 
-        let client = reqwest::Client::new();
-
         // Fetch credits/usage from OpenRouter API
+        let request = client
+            .get(format!("{}/auth/key", self.base_url))
+            .header("Authorization", format!("Bearer {}", creds.api_key));
         let response = client
-            .get("https://openrouter.ai/api/v1/auth/key")
-            .header("Authorization", format!("Bearer {}", creds.api_key))
-            .send()
+            .send(request)
             .await
             .context("Failed to fetch OpenRouter credits")?;
 
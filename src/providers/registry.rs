@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+
+use super::Provider;
+use crate::auth::LoginOptions;
+use crate::http::HttpClient;
+use crate::storage::SecureStorage;
+
+pub type LoginFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Everything tokstat needs to know about a provider, in one place
+pub struct ProviderInfo {
+    /// Identifier stored in `Account.provider` and accepted by `tokstat login`
+    pub id: &'static str,
+    pub display_name: &'static str,
+    pub icon: &'static str,
+    /// One-line summary shown in the dashboard's getting started guide
+    pub description: &'static str,
+    /// Interactive login flow that stores credentials and the account
+    pub login:
+        for<'a> fn(&'a SecureStorage, &'a HttpClient, &'a str, &'a LoginOptions) -> LoginFuture<'a>,
+    /// Build the provider, using the given base URL instead of the default if set
+    pub create: fn(Option<&str>) -> Box<dyn Provider>,
+    /// Credentials JSON for a bare API key or token from an account's `credentials_from`
+    pub credentials: fn(&str) -> String,
+}
+
+pub const PROVIDERS: &[ProviderInfo] = &[
+    ProviderInfo {
+        id: "anthropic",
+        display_name: "Anthropic",
+        icon: "🧠",
+        description: "Claude usage and cost reports",
+        login: |storage, client, name, options| {
+            Box::pin(crate::auth::anthropic::login(
+                storage, client, name, options,
+            ))
+        },
+        create: |base_url| match base_url {
+            Some(url) => Box::new(super::anthropic::AnthropicProvider::with_base_url(url)),
+            None => Box::new(super::anthropic::AnthropicProvider::new()),
+        },
+        credentials: |api_key| {
+            serde_json::to_string(&super::anthropic::AnthropicCredentials {
+                api_key: api_key.to_string(),
+            })
+            .expect("credentials serialize")
+        },
+    },
+    ProviderInfo {
+        id: "azure",
+        display_name: "Azure OpenAI",
+        icon: "☁️",
+        description: "Azure-hosted OpenAI models",
+        login: |storage, client, name, options| {
+            Box::pin(crate::auth::azure::login(storage, client, name, options))
+        },
+        create: |base_url| match base_url {
+            Some(url) => Box::new(super::azure::AzureProvider::with_base_url(url)),
+            None => Box::new(super::azure::AzureProvider::new()),
+        },
+        // The resource comes from the account's base URL or AZURE_RESOURCE_NAME
+        credentials: |api_key| {
+            serde_json::to_string(&super::azure::AzureCredentials {
+                api_key: api_key.to_string(),
+                resource_name: String::new(),
+            })
+            .expect("credentials serialize")
+        },
+    },
+    ProviderInfo {
+        id: "copilot",
+        display_name: "GitHub Copilot",
+        icon: "🤖",
+        description: "AI coding assistant",
+        login: |storage, client, name, options| {
+            Box::pin(crate::auth::copilot::login(storage, client, name, options))
+        },
+        create: |base_url| match base_url {
+            Some(url) => Box::new(super::copilot::CopilotProvider::with_base_url(url)),
+            None => Box::new(super::copilot::CopilotProvider::new()),
+        },
+        credentials: |token| {
+            serde_json::to_string(&super::copilot::CopilotCredentials {
+                access_token: token.to_string(),
+            })
+            .expect("credentials serialize")
+        },
+    },
+    ProviderInfo {
+        id: "openai",
+        display_name: "OpenAI",
+        icon: "✨",
+        description: "Organization usage and costs",
+        login: |storage, client, name, options| {
+            Box::pin(crate::auth::openai::login(storage, client, name, options))
+        },
+        create: |base_url| match base_url {
+            Some(url) => Box::new(super::openai::OpenAIProvider::with_base_url(url)),
+            None => Box::new(super::openai::OpenAIProvider::new()),
+        },
+        credentials: |api_key| {
+            serde_json::to_string(&super::openai::OpenAICredentials {
+                api_key: api_key.to_string(),
+            })
+            .expect("credentials serialize")
+        },
+    },
+    ProviderInfo {
+        id: "openrouter",
+        display_name: "OpenRouter",
+        icon: "🌐",
+        description: "LLM API aggregator",
+        login: |storage, client, name, options| {
+            Box::pin(crate::auth::openrouter::login(
+                storage, client, name, options,
+            ))
+        },
+        create: |base_url| match base_url {
+            Some(url) => Box::new(super::openrouter::OpenRouterProvider::with_base_url(url)),
+            None => Box::new(super::openrouter::OpenRouterProvider::new()),
+        },
+        credentials: |api_key| {
+            serde_json::to_string(&super::openrouter::OpenRouterCredentials {
+                api_key: api_key.to_string(),
+            })
+            .expect("credentials serialize")
+        },
+    },
+];
+
+/// Icon used for accounts whose provider is no longer registered
+pub const UNKNOWN_ICON: &str = "🔌";
+
+pub fn find(id: &str) -> Option<&'static ProviderInfo> {
+    PROVIDERS.iter().find(|info| info.id == id)
+}
+
+pub fn ids() -> Vec<&'static str> {
+    PROVIDERS.iter().map(|info| info.id).collect()
+}
+
+pub fn icon(id: &str) -> &'static str {
+    find(id).map(|info| info.icon).unwrap_or(UNKNOWN_ICON)
+}
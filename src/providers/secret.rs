@@ -0,0 +1,139 @@
+//! Keys of config-defined accounts, read from an environment variable or a command
+
+use anyhow::{Context, Result};
+
+use super::registry::ProviderInfo;
+use crate::config::CredentialSource;
+
+/// Credentials for a config-defined account, in the form its provider stores them
+pub(super) async fn resolve_credentials(
+    source: &CredentialSource,
+    info: &ProviderInfo,
+) -> Result<String> {
+    let secret = resolve_secret(source).await?;
+    // A JSON object is taken as complete credentials, e.g. Azure's with the resource
+    if secret.starts_with('{') {
+        Ok(secret)
+    } else {
+        Ok((info.credentials)(&secret))
+    }
+}
+
+/// Read the key of a config-defined account from its environment variable or command
+async fn resolve_secret(source: &crate::config::CredentialSource) -> Result<String> {
+    let secret = match source {
+        CredentialSource::Env(name) => std::env::var(name)
+            .with_context(|| format!("Environment variable {} is not set", name))?,
+        CredentialSource::Command(command) => {
+            let mut process = if cfg!(windows) {
+                let mut process = tokio::process::Command::new("cmd");
+                process.arg("/C");
+                process
+            } else {
+                let mut process = tokio::process::Command::new("sh");
+                process.arg("-c");
+                process
+            };
+            // A command waiting for input would otherwise hang until the fetch times out, and
+            // must not outlive the fetch once it does
+            let output = process
+                .arg(command)
+                .stdin(std::process::Stdio::null())
+                .kill_on_drop(true)
+                .output()
+                .await
+                .with_context(|| format!("Failed to start '{}'", command))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!(
+                    "'{}' exited with {}: {}",
+                    command,
+                    output.status,
+                    stderr.trim()
+                );
+            }
+            String::from_utf8(output.stdout)
+                .with_context(|| format!("'{}' printed invalid UTF-8", command))?
+        }
+    };
+
+    let secret = secret.trim();
+    if secret.is_empty() {
+        match source {
+            CredentialSource::Env(name) => anyhow::bail!("Environment variable {} is empty", name),
+            CredentialSource::Command(command) => anyhow::bail!("'{}' printed nothing", command),
+        }
+    }
+    Ok(secret.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::registry;
+    use crate::testing::lock_env;
+
+    fn api_key(credentials: &str) -> String {
+        let value: serde_json::Value = serde_json::from_str(credentials).unwrap();
+        value["api_key"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn resolve_secret_reads_trimmed_environment_variable() {
+        let _env = lock_env();
+        std::env::set_var("TOKSTAT_TEST_SECRET", "  sk-env\n");
+        std::env::set_var("TOKSTAT_TEST_SECRET_EMPTY", " ");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let resolve =
+            |name: &str| runtime.block_on(resolve_secret(&CredentialSource::Env(name.into())));
+
+        assert_eq!(resolve("TOKSTAT_TEST_SECRET").unwrap(), "sk-env");
+        let error = resolve("TOKSTAT_TEST_SECRET_EMPTY").unwrap_err();
+        assert!(error.to_string().contains("is empty"));
+        let error = resolve("TOKSTAT_TEST_SECRET_UNSET").unwrap_err();
+        assert!(error.to_string().contains("is not set"));
+
+        std::env::remove_var("TOKSTAT_TEST_SECRET");
+        std::env::remove_var("TOKSTAT_TEST_SECRET_EMPTY");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolve_secret_reports_failing_command() {
+        let source = CredentialSource::Command("echo locked >&2; exit 3".into());
+
+        let error = resolve_secret(&source).await.unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("exited with"));
+        assert!(message.contains("locked"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolve_secret_rejects_empty_output() {
+        // `cat` gets no input, so it can't keep the fetch waiting either
+        for command in ["printf ''", "cat"] {
+            let source = CredentialSource::Command(command.into());
+            let error = resolve_secret(&source).await.unwrap_err();
+            assert!(error.to_string().contains("printed nothing"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn config_account_json_credentials_are_passed_through() {
+        let info = registry::find("openrouter").unwrap();
+
+        let plain = resolve_credentials(&CredentialSource::Command("echo sk-plain".into()), info)
+            .await
+            .unwrap();
+        assert_eq!(api_key(&plain), "sk-plain");
+
+        let json = CredentialSource::Command(r#"echo '{"api_key":"sk-json"}'"#.into());
+        let json = resolve_credentials(&json, info).await.unwrap();
+        assert_eq!(json, r#"{"api_key":"sk-json"}"#);
+    }
+}
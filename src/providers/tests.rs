@@ -0,0 +1,538 @@
+//! Provider parsing against a local HTTP stub
+
+use super::anthropic::AnthropicProvider;
+use super::azure::AzureProvider;
+use super::copilot::CopilotProvider;
+use super::openai::OpenAIProvider;
+use super::openrouter::OpenRouterProvider;
+use super::Provider;
+use crate::testing::{client, Stub};
+
+fn date(value: &str) -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .unwrap()
+        .with_timezone(&chrono::Utc)
+}
+
+const OPENROUTER_CREDENTIALS: &str = r#"{"api_key":"sk-or-test"}"#;
+
+#[tokio::test]
+async fn openrouter_reports_usage_against_limit() {
+    let stub = Stub::start(&[(
+        "/api/v1/auth/key",
+        200,
+        r#"{"data":{"label":"test","usage":12.5,"limit":50.0}}"#,
+    )])
+    .await;
+    let provider = OpenRouterProvider::with_base_url(format!("{}/api/v1", stub.base_url));
+
+    let quota = provider
+        .fetch_quota(&client(), OPENROUTER_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.cost, Some(12.5));
+    assert_eq!(quota.limits.unwrap().max_cost, Some(50.0));
+    assert!(stub.requests()[0].contains("authorization: Bearer sk-or-test"));
+}
+
+#[tokio::test]
+async fn openrouter_without_limit_is_unlimited() {
+    let stub = Stub::start(&[
+        (
+            "/null/auth/key",
+            200,
+            r#"{"data":{"usage":3.0,"limit":null}}"#,
+        ),
+        ("/missing/auth/key", 200, r#"{"data":{"usage":3.0}}"#),
+    ])
+    .await;
+
+    for path in ["null", "missing"] {
+        let provider = OpenRouterProvider::with_base_url(format!("{}/{}", stub.base_url, path));
+        let quota = provider
+            .fetch_quota(&client(), OPENROUTER_CREDENTIALS)
+            .await
+            .unwrap();
+        assert_eq!(quota.usage.cost, Some(3.0));
+        assert_eq!(quota.limits.unwrap().max_cost, None);
+    }
+}
+
+#[tokio::test]
+async fn openrouter_rejects_missing_usage() {
+    let stub = Stub::start(&[("/auth/key", 200, r#"{"data":{"limit":10.0}}"#)]).await;
+    let provider = OpenRouterProvider::with_base_url(&stub.base_url);
+
+    let error = provider
+        .fetch_quota(&client(), OPENROUTER_CREDENTIALS)
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", error).contains("Failed to parse OpenRouter response"));
+}
+
+#[tokio::test]
+async fn openrouter_reports_http_errors() {
+    let stub = Stub::start(&[("/auth/key", 401, r#"{"error":"invalid key"}"#)]).await;
+    let provider = OpenRouterProvider::with_base_url(&stub.base_url);
+
+    let error = provider
+        .fetch_quota(&client(), OPENROUTER_CREDENTIALS)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("401"));
+}
+
+fn copilot_credentials() -> String {
+    serde_json::json!({"access_token": "gho_test"}).to_string()
+}
+
+async fn fetch_copilot(body: &str) -> super::QuotaInfo {
+    let stub = Stub::start(&[("/copilot_internal/user", 200, body)]).await;
+    CopilotProvider::with_base_url(&stub.base_url)
+        .fetch_quota(&client(), &copilot_credentials())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn copilot_reports_premium_requests() {
+    let quota = fetch_copilot(
+        r#"{
+            "quota_reset_date": "2026-11-01T00:00:00Z",
+            "quota_snapshots": {
+                "premium_interactions": {"entitlement": 300, "remaining": 120, "unlimited": false}
+            }
+        }"#,
+    )
+    .await;
+
+    assert_eq!(quota.usage.requests_made, Some(180));
+    assert_eq!(quota.limits.unwrap().max_requests, Some(300));
+    assert_eq!(quota.reset_date, Some(date("2026-11-01T00:00:00Z")));
+}
+
+#[tokio::test]
+async fn copilot_unlimited_snapshot_has_no_limit() {
+    let quota = fetch_copilot(
+        r#"{"quota_snapshots": {"premium_interactions": {"entitlement": 0, "remaining": 0, "unlimited": true}}}"#,
+    )
+    .await;
+
+    assert_eq!(quota.usage.requests_made, None);
+    assert_eq!(quota.limits.unwrap().max_requests, None);
+}
+
+#[tokio::test]
+async fn copilot_tolerates_missing_fields() {
+    for body in [
+        r#"{}"#,
+        r#"{"quota_snapshots": {}}"#,
+        r#"{"quota_snapshots": {"premium_interactions": {"entitlement": 300}}}"#,
+        r#"{"quota_reset_date": "next month"}"#,
+    ] {
+        let quota = fetch_copilot(body).await;
+        assert_eq!(quota.usage.requests_made, None, "{}", body);
+        assert_eq!(quota.limits.unwrap().max_requests, None, "{}", body);
+        assert_eq!(quota.reset_date, None, "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn copilot_parses_date_only_reset() {
+    let quota = fetch_copilot(r#"{"quota_reset_date": "2026-11-01"}"#).await;
+
+    assert_eq!(quota.reset_date, Some(date("2026-11-01T00:00:00Z")));
+}
+
+#[tokio::test]
+async fn copilot_reports_http_errors() {
+    let stub = Stub::start(&[
+        ("/unauthorized/copilot_internal/user", 401, "{}"),
+        ("/forbidden/copilot_internal/user", 403, "{}"),
+    ])
+    .await;
+
+    for (path, expected) in [
+        ("unauthorized", "revoked"),
+        ("forbidden", "Access denied"),
+        ("missing", "not accessible (404)"),
+    ] {
+        let provider = CopilotProvider::with_base_url(format!("{}/{}", stub.base_url, path));
+        let error = provider
+            .fetch_quota(&client(), &copilot_credentials())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains(expected), "{}: {}", path, error);
+    }
+}
+
+#[tokio::test]
+async fn copilot_ignores_expiry_of_old_credentials() {
+    // Older versions stored a made-up expiry 90 days after login
+    let credentials = serde_json::json!({
+        "access_token": "gho_test",
+        "refresh_token": "",
+        "expires_at": "2020-01-01T00:00:00Z",
+    })
+    .to_string();
+    let stub = Stub::start(&[("/copilot_internal/user", 200, "{}")]).await;
+
+    CopilotProvider::with_base_url(&stub.base_url)
+        .fetch_quota(&client(), &credentials)
+        .await
+        .unwrap();
+    assert!(stub.requests()[0].contains("authorization: Bearer gho_test"));
+}
+
+#[tokio::test]
+async fn copilot_fetches_github_user() {
+    let stub = Stub::start(&[
+        (
+            "/public/user",
+            200,
+            r#"{"login":"octocat","email":"octocat@github.com"}"#,
+        ),
+        ("/private/user", 200, r#"{"login":"octocat","email":null}"#),
+        ("/revoked/user", 401, r#"{"message":"Bad credentials"}"#),
+    ])
+    .await;
+    let provider =
+        |path: &str| CopilotProvider::with_base_url(format!("{}/{}", stub.base_url, path));
+
+    let user = provider("public")
+        .fetch_user(&client(), "gho_test")
+        .await
+        .unwrap();
+    assert_eq!(user.describe(), "octocat <octocat@github.com>");
+
+    let user = provider("private")
+        .fetch_user(&client(), "gho_test")
+        .await
+        .unwrap();
+    assert_eq!(user.describe(), "octocat");
+
+    let error = provider("revoked")
+        .fetch_user(&client(), "gho_test")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("revoked"), "{}", error);
+}
+
+const AZURE_CREDENTIALS: &str = r#"{"api_key":"azure-test","resource_name":""}"#;
+const AZURE_MODELS: &str = r#"{"data":[{"id":"gpt-4o"}]}"#;
+
+#[tokio::test]
+async fn azure_sums_usage_and_rate_limits() {
+    let stub = Stub::start(&[
+        ("/openai/models", 200, AZURE_MODELS),
+        (
+            "/openai/usage",
+            200,
+            r#"{"data":[{"total_tokens":1000,"total_requests":10},{"total_tokens":500}]}"#,
+        ),
+        (
+            "/openai/deployments",
+            200,
+            r#"{"data":[
+                {"id":"a","rate_limits":[
+                    {"key":"token","renewal_period_tokens":30000},
+                    {"key":"request","renewal_period_requests":180}
+                ]},
+                {"id":"b","rate_limits":[{"key":"token","renewal_period_tokens":10000}]}
+            ]}"#,
+        ),
+    ])
+    .await;
+    let provider = AzureProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), AZURE_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.tokens_used, Some(1500));
+    assert_eq!(quota.usage.requests_made, Some(10));
+    let limits = quota.limits.unwrap();
+    assert_eq!(limits.max_tokens, Some(40000));
+    assert_eq!(limits.max_requests, Some(180));
+    assert!(stub.requests()[0].contains("api-key: azure-test"));
+}
+
+#[tokio::test]
+async fn azure_deployments_without_rate_limits_have_no_limits() {
+    // The usage endpoint isn't routed, so it answers 404 like on most resources
+    let stub = Stub::start(&[
+        ("/openai/models", 200, AZURE_MODELS),
+        (
+            "/openai/deployments",
+            200,
+            r#"{"data":[{"id":"a","model":"gpt-4o"},{"id":"b","rate_limits":null}]}"#,
+        ),
+    ])
+    .await;
+    let provider = AzureProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), AZURE_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.tokens_used, None);
+    assert_eq!(quota.usage.requests_made, None);
+    let limits = quota.limits.unwrap();
+    assert_eq!(limits.max_tokens, None);
+    assert_eq!(limits.max_requests, None);
+}
+
+#[tokio::test]
+async fn azure_reports_http_errors() {
+    let stub = Stub::start(&[
+        ("/unauthorized/openai/models", 401, r#"{"error":"bad key"}"#),
+        ("/forbidden/openai/models", 403, r#"{"error":"forbidden"}"#),
+    ])
+    .await;
+
+    for (path, expected) in [
+        ("unauthorized", "401"),
+        ("forbidden", "403"),
+        ("missing", "404"),
+    ] {
+        let provider = AzureProvider::with_base_url(format!("{}/{}", stub.base_url, path));
+        let error = provider
+            .fetch_quota(&client(), AZURE_CREDENTIALS)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains(expected), "{}: {}", path, error);
+    }
+}
+
+const ANTHROPIC_CREDENTIALS: &str = r#"{"api_key":"sk-ant-admin-test"}"#;
+
+#[tokio::test]
+async fn anthropic_sums_tokens_and_costs_across_pages() {
+    let stub = Stub::start(&[
+        (
+            "/v1/organizations/usage_report/messages",
+            200,
+            r#"{"data":[{"results":[{
+                "uncached_input_tokens":100,
+                "cache_creation":{"ephemeral_1h_input_tokens":20,"ephemeral_5m_input_tokens":30},
+                "cache_read_input_tokens":40,
+                "output_tokens":10
+            }]}],"has_more":true,"next_page":"usage-2"}"#,
+        ),
+        (
+            "/v1/organizations/usage_report/messages?page=usage-2",
+            200,
+            r#"{"data":[{"results":[{"uncached_input_tokens":5,"output_tokens":5}]}],"has_more":false,"next_page":null}"#,
+        ),
+        (
+            "/v1/organizations/cost_report",
+            200,
+            r#"{"data":[{"results":[{"amount":"1250.5"},{"amount":"49.5"}]}],"has_more":true,"next_page":"cost-2"}"#,
+        ),
+        (
+            "/v1/organizations/cost_report?page=cost-2",
+            200,
+            r#"{"data":[{"results":[{"amount":"200"}]}],"has_more":false}"#,
+        ),
+    ])
+    .await;
+    let provider = AnthropicProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), ANTHROPIC_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.tokens_used, Some(210));
+    assert_eq!(quota.usage.cost, Some(15.0));
+    let requests = stub.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[0].contains("x-api-key: sk-ant-admin-test"));
+    assert!(requests[0].contains("anthropic-version: 2023-06-01"));
+    assert!(requests[1].contains("page=usage-2"));
+    assert!(requests[3].contains("page=cost-2"));
+}
+
+#[tokio::test]
+async fn anthropic_stops_when_no_more_pages() {
+    // `next_page` alone doesn't continue a report whose `has_more` is false
+    let stub = Stub::start(&[
+        (
+            "/v1/organizations/usage_report/messages",
+            200,
+            r#"{"data":[{"results":[{"output_tokens":7}]}],"has_more":false,"next_page":"ignored"}"#,
+        ),
+        (
+            "/v1/organizations/cost_report",
+            200,
+            r#"{"data":[],"has_more":false}"#,
+        ),
+    ])
+    .await;
+    let provider = AnthropicProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), ANTHROPIC_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.tokens_used, Some(7));
+    assert_eq!(quota.usage.cost, Some(0.0));
+    assert_eq!(stub.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn anthropic_requires_admin_key() {
+    let stub = Stub::start(&[
+        (
+            "/unauthorized/v1/organizations/usage_report/messages",
+            401,
+            r#"{"error":{"type":"authentication_error"}}"#,
+        ),
+        (
+            "/forbidden/v1/organizations/usage_report/messages",
+            403,
+            r#"{"error":{"type":"permission_error"}}"#,
+        ),
+    ])
+    .await;
+
+    for path in ["unauthorized", "forbidden"] {
+        let provider = AnthropicProvider::with_base_url(format!("{}/{}", stub.base_url, path));
+        let error = provider
+            .fetch_quota(&client(), ANTHROPIC_CREDENTIALS)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("Admin API key"),
+            "{}: {}",
+            path,
+            error
+        );
+    }
+}
+
+const OPENAI_CREDENTIALS: &str = r#"{"api_key":"sk-admin-test"}"#;
+
+/// Value of a query parameter in a request captured by the stub
+fn query_param(request: &str, name: &str) -> Option<String> {
+    let target = request.split_whitespace().nth(1)?;
+    let (_, query) = target.split_once('?')?;
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| value.to_string())
+    })
+}
+
+#[tokio::test]
+async fn openai_sums_usage_and_costs_across_pages() {
+    let stub = Stub::start(&[
+        (
+            "/v1/organization/usage/completions",
+            200,
+            r#"{"data":[{"results":[
+                {"input_tokens":100,"output_tokens":50,"num_model_requests":3},
+                {"input_tokens":10,"output_tokens":5,"num_model_requests":1}
+            ]}],"has_more":true,"next_page":"usage-2"}"#,
+        ),
+        (
+            "/v1/organization/usage/completions?page=usage-2",
+            200,
+            r#"{"data":[{"results":[{"input_tokens":1000,"num_model_requests":2}]},{"results":[]}],"has_more":false}"#,
+        ),
+        (
+            "/v1/organization/costs",
+            200,
+            r#"{"data":[{"results":[
+                {"amount":{"value":1.25,"currency":"usd"}},
+                {"amount":null}
+            ]}],"has_more":true,"next_page":"cost-2"}"#,
+        ),
+        (
+            "/v1/organization/costs?page=cost-2",
+            200,
+            r#"{"data":[{"results":[{"amount":{"value":0.75,"currency":"usd"}}]}],"has_more":false}"#,
+        ),
+    ])
+    .await;
+    let provider = OpenAIProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), OPENAI_CREDENTIALS)
+        .await
+        .unwrap();
+
+    assert_eq!(quota.usage.tokens_used, Some(1165));
+    assert_eq!(quota.usage.requests_made, Some(6));
+    assert_eq!(quota.usage.cost, Some(2.0));
+    assert_eq!(quota.limits, None);
+    let requests = stub.requests();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[0].contains("authorization: Bearer sk-admin-test"));
+    assert_eq!(
+        query_param(&requests[1], "page").as_deref(),
+        Some("usage-2")
+    );
+    assert_eq!(query_param(&requests[3], "page").as_deref(), Some("cost-2"));
+}
+
+#[tokio::test]
+async fn openai_queries_the_current_month() {
+    let stub = Stub::start(&[
+        ("/v1/organization/usage/completions", 200, r#"{"data":[]}"#),
+        ("/v1/organization/costs", 200, r#"{"data":[]}"#),
+    ])
+    .await;
+    let provider = OpenAIProvider::with_base_url(&stub.base_url);
+
+    let quota = provider
+        .fetch_quota(&client(), OPENAI_CREDENTIALS)
+        .await
+        .unwrap();
+
+    let (start, reset) = super::current_month_period(quota.last_updated);
+    assert_eq!(quota.reset_date, Some(reset));
+    for request in stub.requests() {
+        assert_eq!(
+            query_param(&request, "start_time"),
+            Some(start.timestamp().to_string())
+        );
+        assert_eq!(
+            query_param(&request, "end_time"),
+            Some(quota.last_updated.timestamp().to_string())
+        );
+        assert_eq!(query_param(&request, "bucket_width").as_deref(), Some("1d"));
+    }
+}
+
+#[test]
+fn current_month_period_runs_to_the_next_month() {
+    assert_eq!(
+        super::current_month_period(date("2024-02-29T13:45:00Z")),
+        (date("2024-02-01T00:00:00Z"), date("2024-03-01T00:00:00Z"))
+    );
+    assert_eq!(
+        super::current_month_period(date("2024-12-01T00:00:00Z")),
+        (date("2024-12-01T00:00:00Z"), date("2025-01-01T00:00:00Z"))
+    );
+}
+
+#[tokio::test]
+async fn openai_requires_admin_key() {
+    let stub = Stub::start(&[(
+        "/v1/organization/usage/completions",
+        403,
+        r#"{"error":{"message":"insufficient permissions"}}"#,
+    )])
+    .await;
+    let provider = OpenAIProvider::with_base_url(&stub.base_url);
+
+    let error = provider
+        .fetch_quota(&client(), OPENAI_CREDENTIALS)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("admin key"), "{}", error);
+}
@@ -0,0 +1,407 @@
+use crate::config::{DisplayConfig, StatusConfig, UsageLevel};
+use crate::providers::{registry, QuotaInfo};
+use crate::storage::Account;
+use anyhow::Result;
+
+/// Status bar or prompt the one-line summary is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatusFormat {
+    /// JSON with `text`, `tooltip`, `class` and `percentage`
+    Waybar,
+    /// Full text, short text and color lines
+    I3blocks,
+    /// `#[fg=...]` color markup
+    Tmux,
+    /// Plain text for a custom module
+    Starship,
+    /// `%{F...}` color markup
+    Polybar,
+}
+
+/// How a segment is highlighted: by its usage tier, or as a failed fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tier {
+    Usage(UsageLevel),
+    Error,
+}
+
+/// The part of the summary for one account
+struct Segment {
+    icon: &'static str,
+    name: String,
+    /// Highest usage percentage, or the usage itself for accounts without limits
+    value: String,
+    tier: Tier,
+    percent: Option<f64>,
+    tooltip: String,
+}
+
+impl Segment {
+    fn new(account: &Account, result: &Result<QuotaInfo>, display: &DisplayConfig) -> Self {
+        let info = registry::find(&account.provider);
+        let icon = info.map(|info| info.icon).unwrap_or("•");
+        let provider = info
+            .map(|info| info.display_name)
+            .unwrap_or(account.provider.as_str());
+
+        let quota = match result {
+            Ok(quota) => quota,
+            Err(e) => {
+                return Self {
+                    icon,
+                    name: account.name.clone(),
+                    value: "!".to_string(),
+                    tier: Tier::Error,
+                    percent: None,
+                    tooltip: format!("{} {} ({}): {}", icon, account.name, provider, e),
+                }
+            }
+        };
+
+        let metrics = quota.usage_against_limits();
+        let percent = metrics
+            .iter()
+            .map(|(_, used, limit)| used / limit * 100.0)
+            .reduce(f64::max);
+        let value = match percent {
+            Some(percent) => format!("{:.0}%", percent),
+            None => match (
+                quota.usage.cost,
+                quota.usage.tokens_used,
+                quota.usage.requests_made,
+            ) {
+                (Some(cost), _, _) => format!("${:.2}", cost),
+                (None, Some(tokens), _) => crate::format::format_number(tokens),
+                (None, None, Some(requests)) => crate::format::format_number(requests),
+                (None, None, None) => "-".to_string(),
+            },
+        };
+
+        let mut details: Vec<String> = metrics
+            .iter()
+            .map(|(metric, used, limit)| {
+                let percent = used / limit * 100.0;
+                let (used, limit) = match *metric {
+                    "cost" => (format!("${:.2}", used), format!("${:.2}", limit)),
+                    _ => (
+                        crate::format::format_number(*used as u64),
+                        crate::format::format_number(*limit as u64),
+                    ),
+                };
+                format!("{} {}/{} ({:.0}%)", metric, used, limit, percent)
+            })
+            .collect();
+        if details.is_empty() {
+            details.push(value.clone());
+        }
+        if let Some(reset) = quota.reset_date {
+            details.push(format!("resets {}", display.format_datetime(reset)));
+        }
+
+        Self {
+            icon,
+            name: account.name.clone(),
+            value,
+            tier: Tier::Usage(
+                percent
+                    .map(|percent| display.usage_level(percent))
+                    .unwrap_or(UsageLevel::Normal),
+            ),
+            percent,
+            tooltip: format!(
+                "{} {} ({}): {}",
+                icon,
+                account.name,
+                provider,
+                details.join(", ")
+            ),
+        }
+    }
+
+    fn text(&self, show_names: bool) -> String {
+        if show_names {
+            format!("{} {} {}", self.icon, self.name, self.value)
+        } else {
+            format!("{} {}", self.icon, self.value)
+        }
+    }
+}
+
+/// Failed fetches outrank normal usage, but an account over a threshold is shown first
+fn tier_rank(tier: Tier) -> u8 {
+    match tier {
+        Tier::Usage(UsageLevel::Normal) => 0,
+        Tier::Error => 1,
+        Tier::Usage(UsageLevel::Warning) => 2,
+        Tier::Usage(UsageLevel::Critical) => 3,
+    }
+}
+
+fn class_name(tier: Tier) -> &'static str {
+    match tier {
+        Tier::Usage(UsageLevel::Normal) => "normal",
+        Tier::Usage(UsageLevel::Warning) => "warning",
+        Tier::Usage(UsageLevel::Critical) => "critical",
+        Tier::Error => "error",
+    }
+}
+
+/// Hex color for a tier, `None` keeps the bar's default color
+fn tier_color(tier: Tier) -> Option<&'static str> {
+    match tier {
+        Tier::Usage(UsageLevel::Normal) => None,
+        Tier::Usage(UsageLevel::Warning) => Some("#f0c674"),
+        Tier::Usage(UsageLevel::Critical) => Some("#cc6666"),
+        Tier::Error => Some("#de935f"),
+    }
+}
+
+/// Accounts to show: the ones given on the command line, else `status.accounts`, else all
+pub fn select_accounts(
+    accounts: Vec<Account>,
+    only: &[String],
+    config: &StatusConfig,
+) -> Result<Vec<Account>> {
+    let only = if only.is_empty() {
+        &config.accounts
+    } else {
+        only
+    };
+    if only.is_empty() {
+        return Ok(accounts);
+    }
+
+    only.iter()
+        .map(|name| {
+            accounts
+                .iter()
+                .find(|account| account.name == *name)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Account '{}' not found", name))
+        })
+        .collect()
+}
+
+/// One-line summary of `accounts` in the given status bar format
+pub fn render(
+    format: StatusFormat,
+    accounts: &[Account],
+    results: &[Result<QuotaInfo>],
+    display: &DisplayConfig,
+    config: &StatusConfig,
+) -> String {
+    let segments: Vec<Segment> = accounts
+        .iter()
+        .zip(results)
+        .map(|(account, result)| Segment::new(account, result, display))
+        .collect();
+
+    let worst = segments
+        .iter()
+        .max_by_key(|segment| tier_rank(segment.tier));
+    let tier = worst
+        .map(|segment| segment.tier)
+        .unwrap_or(Tier::Usage(UsageLevel::Normal));
+    let plain = segments
+        .iter()
+        .map(|segment| segment.text(config.show_names))
+        .collect::<Vec<_>>()
+        .join(&config.separator);
+    let tooltip = segments
+        .iter()
+        .map(|segment| segment.tooltip.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match format {
+        StatusFormat::Waybar => {
+            let percentage = segments
+                .iter()
+                .filter_map(|segment| segment.percent)
+                .reduce(f64::max)
+                .unwrap_or_default();
+            serde_json::json!({
+                "text": plain,
+                "tooltip": tooltip,
+                "class": class_name(tier),
+                "percentage": percentage.round() as u64,
+            })
+            .to_string()
+        }
+        StatusFormat::I3blocks => {
+            // i3blocks reads full text, short text and color from consecutive lines
+            let short = worst.map(|segment| segment.text(false)).unwrap_or_default();
+            match tier_color(tier) {
+                Some(color) => format!("{}\n{}\n{}", plain, short, color),
+                None => format!("{}\n{}", plain, short),
+            }
+        }
+        StatusFormat::Tmux => colored_segments(
+            &segments,
+            config,
+            // tmux expands `#` sequences, an account name like "team#1" must stay literal
+            |text| text.replace('#', "##"),
+            |text, color| format!("#[fg={}]{}#[default]", color, text),
+        ),
+        StatusFormat::Polybar => {
+            colored_segments(&segments, config, str::to_string, |text, color| {
+                format!("%{{F{}}}{}%{{F-}}", color, text)
+            })
+        }
+        StatusFormat::Starship => plain,
+    }
+}
+
+/// Color each segment by its own tier, leaving normal ones in the default color.
+/// `escape` protects the text of every segment from the bar's markup.
+fn colored_segments(
+    segments: &[Segment],
+    config: &StatusConfig,
+    escape: impl Fn(&str) -> String,
+    colorize: impl Fn(&str, &str) -> String,
+) -> String {
+    segments
+        .iter()
+        .map(|segment| {
+            let text = escape(&segment.text(config.show_names));
+            match tier_color(segment.tier) {
+                Some(color) => colorize(&text, color),
+                None => text,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&config.separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{TokenLimits, TokenUsage};
+
+    fn account(name: &str) -> Account {
+        Account {
+            name: name.to_string(),
+            provider: "openrouter".to_string(),
+            email: None,
+            created_at: chrono::Utc::now(),
+            last_updated: chrono::Utc::now(),
+            budget: None,
+            base_url: None,
+            credentials_from: None,
+        }
+    }
+
+    /// Quota with `cost` spent out of $100
+    fn quota(cost: f64) -> Result<QuotaInfo> {
+        Ok(QuotaInfo {
+            provider: "openrouter".to_string(),
+            account_name: String::new(),
+            usage: TokenUsage {
+                tokens_used: None,
+                requests_made: None,
+                cost: Some(cost),
+            },
+            limits: Some(TokenLimits {
+                max_cost: Some(100.0),
+                ..Default::default()
+            }),
+            reset_date: None,
+            last_updated: chrono::Utc::now(),
+        })
+    }
+
+    fn named() -> StatusConfig {
+        StatusConfig {
+            show_names: true,
+            separator: " | ".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn waybar(accounts: &[Account], results: &[Result<QuotaInfo>]) -> serde_json::Value {
+        let output = render(
+            StatusFormat::Waybar,
+            accounts,
+            results,
+            &DisplayConfig::default(),
+            &named(),
+        );
+        serde_json::from_str(&output).unwrap()
+    }
+
+    #[test]
+    fn waybar_output_is_json_with_the_worst_class() {
+        let output = waybar(
+            &[account("work"), account("home")],
+            &[quota(85.0), quota(20.0)],
+        );
+
+        let icon = registry::find("openrouter").unwrap().icon;
+        assert_eq!(
+            output["text"],
+            format!("{} work 85% | {} home 20%", icon, icon)
+        );
+        assert_eq!(output["class"], "critical");
+        assert_eq!(output["percentage"], 85);
+        let tooltip = output["tooltip"].as_str().unwrap();
+        assert!(tooltip.contains("work (OpenRouter): cost $85.00/$100.00 (85%)"));
+        assert_eq!(tooltip.lines().count(), 2);
+    }
+
+    #[test]
+    fn errors_outrank_normal_usage_but_not_warnings() {
+        let accounts = [account("work"), account("broken")];
+        let failed = || Err(anyhow::anyhow!("timed out"));
+
+        let output = waybar(&accounts, &[quota(10.0), failed()]);
+        assert_eq!(output["class"], "error");
+        assert!(output["text"].as_str().unwrap().ends_with("broken !"));
+        assert!(output["tooltip"].as_str().unwrap().contains("timed out"));
+
+        let output = waybar(&accounts, &[quota(60.0), failed()]);
+        assert_eq!(output["class"], "warning");
+    }
+
+    #[test]
+    fn tmux_escapes_hashes_in_every_segment() {
+        let output = render(
+            StatusFormat::Tmux,
+            &[account("team#1"), account("ops#2")],
+            &[quota(90.0), quota(10.0)],
+            &DisplayConfig::default(),
+            &named(),
+        );
+
+        let icon = registry::find("openrouter").unwrap().icon;
+        assert_eq!(
+            output,
+            format!(
+                "#[fg=#cc6666]{} team##1 90%#[default] | {} ops##2 10%",
+                icon, icon
+            )
+        );
+    }
+
+    #[test]
+    fn select_accounts_prefers_command_line_over_config() {
+        let accounts = vec![account("work"), account("home"), account("ci")];
+        let config = StatusConfig {
+            accounts: vec!["ci".to_string()],
+            ..Default::default()
+        };
+        let names = |selected: Vec<Account>| -> Vec<String> {
+            selected.into_iter().map(|account| account.name).collect()
+        };
+
+        let selected = select_accounts(accounts.clone(), &[], &StatusConfig::default()).unwrap();
+        assert_eq!(names(selected), ["work", "home", "ci"]);
+        let selected = select_accounts(accounts.clone(), &[], &config).unwrap();
+        assert_eq!(names(selected), ["ci"]);
+        let only = ["home".to_string(), "work".to_string()];
+        let selected = select_accounts(accounts.clone(), &only, &config).unwrap();
+        assert_eq!(names(selected), ["home", "work"]);
+
+        let error = select_accounts(accounts, &["gone".to_string()], &config).unwrap_err();
+        assert!(error.to_string().contains("'gone' not found"));
+    }
+}
@@ -0,0 +1,701 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::{CredentialBackend, CredentialsConfig};
+
+const KEYRING_SERVICE: &str = "tokstat";
+const PASSPHRASE_ENV: &str = "TOKSTAT_VAULT_PASSPHRASE";
+const KEY_FILE_ENV: &str = "TOKSTAT_VAULT_KEY_FILE";
+const VAULT_VERSION: u32 = 1;
+/// PBKDF2-HMAC-SHA256 rounds for new vaults, as recommended by OWASP
+#[cfg(not(test))]
+const KDF_ITERATIONS: u32 = 600_000;
+/// Unoptimized test builds would spend seconds on every vault
+#[cfg(test)]
+const KDF_ITERATIONS: u32 = 1_000;
+
+/// A place credentials can be kept, as opposed to the `auto` backend setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StoreKind {
+    /// The system keyring (Secret Service, macOS Keychain, Windows Credential Manager)
+    Keyring,
+    /// The AES-GCM encrypted vault file in the config directory
+    Vault,
+}
+
+impl StoreKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyring => "keyring",
+            Self::Vault => "vault",
+        }
+    }
+}
+
+/// Credentials of every account, in the backend selected by `credentials.backend`
+#[derive(Clone)]
+pub struct CredentialStore {
+    backend: CredentialBackend,
+    vault: Vault,
+}
+
+impl CredentialStore {
+    pub fn new(config_dir: &Path) -> Self {
+        Self {
+            backend: CredentialBackend::Auto,
+            vault: Vault {
+                path: config_dir.join("credentials.vault"),
+                key_file: None,
+                unlocked: Arc::default(),
+                writing: Arc::default(),
+            },
+        }
+    }
+
+    /// Apply the `[credentials]` settings, keeping an already unlocked vault unlocked
+    pub fn configure(&mut self, config: &CredentialsConfig) {
+        self.backend = config.backend;
+        self.vault.key_file =
+            (!config.vault_key_file.is_empty()).then(|| PathBuf::from(&config.vault_key_file));
+    }
+
+    pub fn vault_path(&self) -> &Path {
+        &self.vault.path
+    }
+
+    pub fn get(&self, account: &str) -> Result<String> {
+        match self.backend {
+            CredentialBackend::Keyring => self.get_from(StoreKind::Keyring, account),
+            CredentialBackend::Vault => self.get_from(StoreKind::Vault, account),
+            CredentialBackend::Auto => match keyring_get(account) {
+                Ok(secret) => Ok(secret),
+                Err(e) if self.vault.exists() => {
+                    tracing::debug!("Keyring lookup for {} failed: {}", account, e);
+                    self.vault.get(account)
+                }
+                Err(e) => Err(e).context("Failed to retrieve credentials from keyring"),
+            },
+        }
+    }
+
+    /// Store an account's credentials; without `interactive`, a vault passphrase that isn't
+    /// in the environment or key file is an error instead of a prompt
+    pub fn set(&self, account: &str, secret: &str, interactive: bool) -> Result<()> {
+        match self.backend {
+            CredentialBackend::Keyring => self.set_in(StoreKind::Keyring, account, secret),
+            CredentialBackend::Vault => self.vault.set(account, secret, interactive),
+            // Once there is a vault, e.g. after `credentials migrate --to vault`, keep using it
+            CredentialBackend::Auto if self.vault.exists() => {
+                self.vault.set(account, secret, interactive)
+            }
+            CredentialBackend::Auto => match keyring_set(account, secret) {
+                Ok(()) => Ok(()),
+                Err(e) if keyring_unavailable(&e) => {
+                    tracing::info!("No keyring available ({}), using the vault", e);
+                    self.vault.set(account, secret, interactive)
+                }
+                Err(e) => Err(e).context("Failed to store credentials in keyring"),
+            },
+        }
+    }
+
+    pub fn delete(&self, account: &str) -> Result<()> {
+        match self.backend {
+            CredentialBackend::Keyring => self.delete_from(StoreKind::Keyring, account),
+            CredentialBackend::Vault => self.delete_from(StoreKind::Vault, account),
+            CredentialBackend::Auto => {
+                self.delete_from(StoreKind::Keyring, account)?;
+                if self.vault.exists() {
+                    self.vault.delete(account)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn get_from(&self, kind: StoreKind, account: &str) -> Result<String> {
+        match kind {
+            StoreKind::Keyring => {
+                keyring_get(account).context("Failed to retrieve credentials from keyring")
+            }
+            StoreKind::Vault => self.vault.get(account),
+        }
+    }
+
+    pub fn set_in(&self, kind: StoreKind, account: &str, secret: &str) -> Result<()> {
+        match kind {
+            StoreKind::Keyring => {
+                keyring_set(account, secret).context("Failed to store credentials in keyring")
+            }
+            StoreKind::Vault => self.vault.set(account, secret, true),
+        }
+    }
+
+    /// Remove an account's credentials, succeeding if there were none
+    pub fn delete_from(&self, kind: StoreKind, account: &str) -> Result<()> {
+        match kind {
+            StoreKind::Keyring => match keyring::Entry::new(KEYRING_SERVICE, account)
+                .and_then(|entry| entry.delete_password())
+            {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                // Nothing can be stored in a keyring that isn't there
+                Err(e) if keyring_unavailable(&e) => Ok(()),
+                Err(e) => Err(e).context("Failed to delete credentials from keyring"),
+            },
+            StoreKind::Vault => self.vault.delete(account),
+        }
+    }
+
+    /// Ask for the vault passphrase now if the vault will be needed. This is the only place
+    /// an existing vault prompts; reads from fetch tasks fail instead of blocking on the
+    /// terminal. Fetches call it once stored credentials are about to be read, the dashboard
+    /// before it takes over the terminal.
+    pub fn unlock_if_needed(&self) -> Result<()> {
+        match self.backend {
+            CredentialBackend::Keyring => Ok(()),
+            // Under `auto`, accounts may be in either place once a vault exists
+            CredentialBackend::Vault | CredentialBackend::Auto => self.unlock_vault(),
+        }
+    }
+
+    /// Where new credentials go: under `auto` an existing vault, else the keyring if there
+    /// is one
+    pub fn default_store(&self) -> StoreKind {
+        match self.backend {
+            CredentialBackend::Keyring => StoreKind::Keyring,
+            CredentialBackend::Vault => StoreKind::Vault,
+            CredentialBackend::Auto if self.vault.exists() || !keyring_available() => {
+                StoreKind::Vault
+            }
+            CredentialBackend::Auto => StoreKind::Keyring,
+        }
+    }
+
+    /// Ask for the passphrase of an existing vault, e.g. before moving credentials out of it
+    pub fn unlock_vault(&self) -> Result<()> {
+        if self.vault.exists() {
+            self.vault.load(true)?;
+        }
+        Ok(())
+    }
+}
+
+fn keyring_get(account: &str) -> keyring::Result<String> {
+    keyring::Entry::new(KEYRING_SERVICE, account)?.get_password()
+}
+
+fn keyring_set(account: &str, secret: &str) -> keyring::Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, account)?.set_password(secret)
+}
+
+fn keyring_unavailable(error: &keyring::Error) -> bool {
+    matches!(
+        error,
+        keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)
+    )
+}
+
+fn keyring_available() -> bool {
+    match keyring_get("tokstat-probe") {
+        Err(e) => !keyring_unavailable(&e),
+        Ok(_) => true,
+    }
+}
+
+/// Encrypted file holding the credentials of all accounts as one JSON object
+#[derive(Clone)]
+struct Vault {
+    path: PathBuf,
+    key_file: Option<PathBuf>,
+    /// Derived key, shared between clones so the passphrase is asked for once per run
+    unlocked: Arc<Mutex<Option<VaultKey>>>,
+    /// Held from load to save within this process; `vault.lock` does the same between processes
+    writing: Arc<Mutex<()>>,
+}
+
+#[derive(Clone)]
+struct VaultKey {
+    key: [u8; 32],
+    salt: Vec<u8>,
+    iterations: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl Vault {
+    fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn get(&self, account: &str) -> Result<String> {
+        if !self.exists() {
+            bail!("No credentials vault at {}", self.path.display());
+        }
+        self.load(false)?
+            .0
+            .remove(account)
+            .with_context(|| format!("No credentials for '{}' in the vault", account))
+    }
+
+    /// Store a secret, creating the vault if needed; `prompt` allows asking for the passphrase
+    fn set(&self, account: &str, secret: &str, prompt: bool) -> Result<()> {
+        self.update(prompt, |entries| {
+            entries.insert(account.to_string(), secret.to_string());
+            true
+        })
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        if !self.exists() {
+            return Ok(());
+        }
+        self.update(false, |entries| entries.remove(account).is_some())
+    }
+
+    /// Load, change and save the vault under both locks, so concurrent logins or
+    /// migrations can't drop each other's entries. `change` returns whether to save.
+    fn update(
+        &self,
+        prompt: bool,
+        change: impl FnOnce(&mut BTreeMap<String, String>) -> bool,
+    ) -> Result<()> {
+        let _writing = self.writing.lock().unwrap_or_else(|e| e.into_inner());
+        let _lock = super::lock_file(&self.path)?;
+
+        let (mut entries, key) = self.load(prompt)?;
+        if change(&mut entries) {
+            self.save(&entries, &key)?;
+        }
+        Ok(())
+    }
+
+    /// Decrypt all entries, or none if the vault doesn't exist yet, along with the key to
+    /// save them with. Without `prompt`, only an unlocked key, the environment or the key
+    /// file are used, so this never blocks on the terminal.
+    fn load(&self, prompt: bool) -> Result<(BTreeMap<String, String>, VaultKey)> {
+        let cached = self
+            .unlocked
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        if !self.exists() {
+            let key = match cached {
+                Some(key) => key,
+                None => {
+                    let passphrase = self.passphrase(true, prompt)?;
+                    let mut salt = vec![0u8; 16];
+                    rand::thread_rng().fill_bytes(&mut salt);
+                    VaultKey {
+                        key: derive_key(passphrase.as_bytes(), &salt, KDF_ITERATIONS),
+                        salt,
+                        iterations: KDF_ITERATIONS,
+                    }
+                }
+            };
+            self.remember(&key);
+            return Ok((BTreeMap::new(), key));
+        }
+
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let file: VaultFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", self.path.display()))?;
+        if file.version != VAULT_VERSION {
+            bail!("Unsupported credentials vault version {}", file.version);
+        }
+        let salt = BASE64.decode(&file.salt).context("Invalid vault salt")?;
+        let nonce = BASE64.decode(&file.nonce).context("Invalid vault nonce")?;
+        let ciphertext = BASE64
+            .decode(&file.ciphertext)
+            .context("Invalid vault ciphertext")?;
+        if nonce.len() != 12 {
+            bail!("Invalid vault nonce");
+        }
+
+        let key = match cached {
+            Some(key) if key.salt == salt && key.iterations == file.iterations => key,
+            _ => {
+                let passphrase = self.passphrase(false, prompt)?;
+                VaultKey {
+                    key: derive_key(passphrase.as_bytes(), &salt, file.iterations),
+                    salt,
+                    iterations: file.iterations,
+                }
+            }
+        };
+
+        let plaintext = Aes256Gcm::new(&key.key.into())
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| anyhow::anyhow!("Wrong vault passphrase or damaged vault file"))?;
+        let entries = serde_json::from_slice(&plaintext).context("Failed to parse vault")?;
+
+        self.remember(&key);
+        Ok((entries, key))
+    }
+
+    fn remember(&self, key: &VaultKey) {
+        *self.unlocked.lock().unwrap_or_else(|e| e.into_inner()) = Some(key.clone());
+    }
+
+    /// Encrypt `entries` with a fresh nonce
+    fn save(&self, entries: &BTreeMap<String, String>, key: &VaultKey) -> Result<()> {
+        let mut nonce = [0u8; 12];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let plaintext = serde_json::to_vec(entries).context("Failed to serialize vault")?;
+        let ciphertext = Aes256Gcm::new(&key.key.into())
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt vault"))?;
+
+        let file = VaultFile {
+            version: VAULT_VERSION,
+            iterations: key.iterations,
+            salt: BASE64.encode(&key.salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let content = serde_json::to_string_pretty(&file).context("Failed to serialize vault")?;
+
+        // Write next to the vault and rename, so a crash never leaves half a vault behind
+        let tmp = self.path.with_extension("vault.tmp");
+        write_private(&tmp, content.as_bytes())?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Passphrase from `TOKSTAT_VAULT_PASSPHRASE`, the key file, or a prompt if allowed
+    fn passphrase(&self, creating: bool, prompt: bool) -> Result<String> {
+        if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+            if !passphrase.is_empty() {
+                return Ok(passphrase);
+            }
+        }
+
+        let key_file = std::env::var_os(KEY_FILE_ENV)
+            .map(PathBuf::from)
+            .or_else(|| self.key_file.clone());
+        if let Some(path) = key_file {
+            let passphrase = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read vault key file {}", path.display()))?;
+            let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+            if passphrase.is_empty() {
+                bail!("Vault key file {} is empty", path.display());
+            }
+            return Ok(passphrase.to_string());
+        }
+
+        if !prompt || !io::stdin().is_terminal() {
+            bail!(
+                "The credentials vault needs a passphrase: set {} or credentials.vault_key_file",
+                PASSPHRASE_ENV
+            );
+        }
+
+        if creating {
+            eprintln!(
+                "No keyring available, credentials are stored encrypted in {}",
+                self.path.display()
+            );
+            let passphrase = read_hidden("New vault passphrase: ")?;
+            if passphrase.is_empty() {
+                bail!("Vault passphrase cannot be empty");
+            }
+            if read_hidden("Repeat passphrase: ")? != passphrase {
+                bail!("Passphrases don't match");
+            }
+            Ok(passphrase)
+        } else {
+            read_hidden("Vault passphrase: ")
+        }
+    }
+}
+
+/// AES-256 key from the passphrase with PBKDF2-HMAC-SHA256
+fn derive_key(passphrase: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, iterations, &mut key);
+    key
+}
+
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Read a line from the terminal without echoing it
+fn read_hidden(prompt: &str) -> Result<String> {
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    enable_raw_mode().context("Failed to read passphrase")?;
+    let mut input = Vec::new();
+    let mut cancelled = false;
+    for byte in io::stdin().lock().bytes() {
+        match byte {
+            Ok(b'\r' | b'\n') => break,
+            Ok(3) => {
+                cancelled = true;
+                break;
+            }
+            Ok(8 | 127) => {
+                // Drop a whole UTF-8 character, not just its last byte
+                while let Some(byte) = input.pop() {
+                    if byte & 0xC0 != 0x80 {
+                        break;
+                    }
+                }
+            }
+            Ok(byte) => input.push(byte),
+            Err(_) => break,
+        }
+    }
+    let _ = disable_raw_mode();
+    eprintln!();
+
+    if cancelled {
+        bail!("Cancelled");
+    }
+    String::from_utf8(input).context("Passphrase is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    // PBKDF2-HMAC-SHA256 vectors from RFC 7914, section 11, cut to the 32 bytes we use;
+    // a guard against the key size or parameter order changing, not a test of the crate
+    #[test]
+    fn derive_key_matches_rfc_7914_vectors() {
+        assert_eq!(
+            hex(&derive_key(b"passwd", b"salt", 1)),
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+        );
+        assert_eq!(
+            hex(&derive_key(b"Password", b"NaCl", 80_000)),
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56"
+        );
+    }
+
+    /// The passphrase comes from the process environment, so vault tests take turns
+    fn lock_env(passphrase: Option<&str>) -> std::sync::MutexGuard<'static, ()> {
+        let guard = crate::testing::lock_env();
+        keyring::set_default_credential_builder(Box::new(MemoryKeyring));
+        set_passphrase(passphrase);
+        guard
+    }
+
+    /// Stands in for the OS keyring, so tests never read or write the developer's own
+    static KEYRING: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+    struct MemoryKeyring;
+
+    struct MemoryEntry(String);
+
+    impl keyring::credential::CredentialBuilderApi for MemoryKeyring {
+        fn build(
+            &self,
+            _target: Option<&str>,
+            _service: &str,
+            user: &str,
+        ) -> keyring::Result<Box<keyring::Credential>> {
+            Ok(Box::new(MemoryEntry(user.to_string())))
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    impl keyring::credential::CredentialApi for MemoryEntry {
+        fn set_password(&self, password: &str) -> keyring::Result<()> {
+            KEYRING
+                .lock()
+                .unwrap()
+                .insert(self.0.clone(), password.to_string());
+            Ok(())
+        }
+
+        fn get_password(&self) -> keyring::Result<String> {
+            KEYRING
+                .lock()
+                .unwrap()
+                .get(&self.0)
+                .cloned()
+                .ok_or(keyring::Error::NoEntry)
+        }
+
+        fn delete_password(&self) -> keyring::Result<()> {
+            KEYRING
+                .lock()
+                .unwrap()
+                .remove(&self.0)
+                .map(|_| ())
+                .ok_or(keyring::Error::NoEntry)
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    fn set_passphrase(passphrase: Option<&str>) {
+        match passphrase {
+            Some(passphrase) => std::env::set_var(PASSPHRASE_ENV, passphrase),
+            None => std::env::remove_var(PASSPHRASE_ENV),
+        }
+        std::env::remove_var(KEY_FILE_ENV);
+    }
+
+    fn vault_store(dir: &Path) -> CredentialStore {
+        let mut store = CredentialStore::new(dir);
+        store.configure(&CredentialsConfig {
+            backend: CredentialBackend::Vault,
+            ..Default::default()
+        });
+        store
+    }
+
+    #[test]
+    fn vault_round_trip() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        let store = vault_store(dir.path());
+
+        store.set("work", "sk-work", false).unwrap();
+        store.set("home", "sk-home", false).unwrap();
+        store.delete("work").unwrap();
+
+        assert_eq!(store.get("home").unwrap(), "sk-home");
+        assert!(store.get("work").is_err());
+        // Deleting what isn't there is fine
+        store.delete("work").unwrap();
+    }
+
+    #[test]
+    fn saved_vault_reloads_in_a_new_run() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        vault_store(dir.path())
+            .set("work", "sk-work", false)
+            .unwrap();
+
+        let store = vault_store(dir.path());
+        store.unlock_if_needed().unwrap();
+        assert_eq!(store.get("work").unwrap(), "sk-work");
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        vault_store(dir.path())
+            .set("work", "sk-work", false)
+            .unwrap();
+
+        set_passphrase(Some("battery staple"));
+        let error = vault_store(dir.path()).get("work").unwrap_err();
+        assert!(format!("{:#}", error).contains("Wrong vault passphrase"));
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        let store = vault_store(dir.path());
+        store.set("work", "sk-work", false).unwrap();
+
+        let path = store.vault_path().to_path_buf();
+        let mut file: VaultFile =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let mut ciphertext = BASE64.decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.ciphertext = BASE64.encode(ciphertext);
+        fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+
+        let error = vault_store(dir.path()).get("work").unwrap_err();
+        assert!(format!("{:#}", error).contains("damaged vault file"));
+    }
+
+    #[test]
+    fn locked_vault_fails_instead_of_prompting() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        vault_store(dir.path())
+            .set("work", "sk-work", false)
+            .unwrap();
+
+        set_passphrase(None);
+        let error = vault_store(dir.path()).get("work").unwrap_err();
+        assert!(format!("{:#}", error).contains(PASSPHRASE_ENV));
+    }
+
+    #[test]
+    fn non_interactive_store_needs_passphrase_from_environment() {
+        let _env = lock_env(None);
+        let dir = tempfile::tempdir().unwrap();
+
+        let error = vault_store(dir.path())
+            .set("work", "sk-work", false)
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains(PASSPHRASE_ENV));
+        assert!(!dir.path().join("credentials.vault").exists());
+    }
+
+    #[test]
+    fn migrating_twice_moves_credentials_once() {
+        let _env = lock_env(Some("correct horse"));
+        let dir = tempfile::tempdir().unwrap();
+        let storage = crate::storage::SecureStorage::in_dir(dir.path().to_path_buf()).unwrap();
+        let account = "migrated";
+        let store = vault_store(dir.path());
+        store
+            .set_in(StoreKind::Keyring, account, "sk-work")
+            .unwrap();
+
+        for (from, to) in [
+            (StoreKind::Keyring, StoreKind::Vault),
+            (StoreKind::Vault, StoreKind::Keyring),
+        ] {
+            assert!(storage.migrate_credentials(account, from, to).unwrap());
+            assert!(!storage.migrate_credentials(account, from, to).unwrap());
+            assert_eq!(store.get_from(to, account).unwrap(), "sk-work");
+            assert!(store.get_from(from, account).is_err());
+        }
+        assert!(storage
+            .migrate_credentials("missing", StoreKind::Keyring, StoreKind::Vault)
+            .is_err());
+    }
+}
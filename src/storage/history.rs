@@ -0,0 +1,388 @@
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use super::{CachedQuota, QuotaSnapshot};
+use crate::config::{HistoryConfig, Retention};
+use crate::providers::QuotaInfo;
+
+/// Quota history kept in SQLite, one row per snapshot, plus the last fetched quota per account
+pub(super) struct HistoryDb {
+    conn: Connection,
+    /// Last run of `downsample_if_due` in this process
+    downsampled_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Layout of the `quota_history.json` file used before the SQLite store
+#[derive(Deserialize)]
+struct LegacyHistory {
+    account_name: String,
+    snapshots: Vec<QuotaSnapshot>,
+}
+
+impl HistoryDb {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context("Failed to open quota history database")?;
+        // The dashboard, `serve` and one-off commands may all write at the same time
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS snapshots (
+                account_name  TEXT NOT NULL,
+                timestamp     INTEGER NOT NULL,
+                tokens_used   INTEGER,
+                requests_made INTEGER,
+                cost          REAL,
+                PRIMARY KEY (account_name, timestamp)
+            );
+            CREATE INDEX IF NOT EXISTS snapshots_timestamp ON snapshots (timestamp);
+            CREATE TABLE IF NOT EXISTS quota_cache (
+                account_name TEXT PRIMARY KEY,
+                fetched_at   INTEGER NOT NULL,
+                quota        TEXT NOT NULL
+            );",
+        )
+        .context("Failed to create quota history tables")?;
+
+        Ok(Self {
+            conn,
+            downsampled_at: None,
+        })
+    }
+
+    /// Import a `quota_history.json` file; rows already present are skipped, so this is safe to
+    /// repeat if the file couldn't be moved away afterwards
+    pub fn import_json(&mut self, json_path: &Path) -> Result<usize> {
+        let content = fs::read_to_string(json_path).context("Failed to read quota history")?;
+        let legacy: Vec<LegacyHistory> =
+            serde_json::from_str(&content).context("Failed to parse quota history")?;
+
+        let tx = self.conn.transaction()?;
+        let mut imported = 0;
+        for history in &legacy {
+            for snapshot in &history.snapshots {
+                imported += insert(&tx, &history.account_name, snapshot)?;
+            }
+        }
+        tx.commit()
+            .context("Failed to import quota history into the database")?;
+
+        Ok(imported)
+    }
+
+    pub fn last_snapshot(&self, account_name: &str) -> Result<Option<QuotaSnapshot>> {
+        self.conn
+            .query_row(
+                "SELECT timestamp, tokens_used, requests_made, cost FROM snapshots
+                 WHERE account_name = ?1 ORDER BY timestamp DESC LIMIT 1",
+                params![account_name],
+                snapshot_from_row,
+            )
+            .optional()
+            .context("Failed to read quota history")
+    }
+
+    pub fn insert(&self, account_name: &str, snapshot: &QuotaSnapshot) -> Result<()> {
+        insert(&self.conn, account_name, snapshot)?;
+        Ok(())
+    }
+
+    /// All snapshots of an account, oldest first
+    pub fn snapshots(&self, account_name: &str) -> Result<Vec<QuotaSnapshot>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT timestamp, tokens_used, requests_made, cost FROM snapshots
+             WHERE account_name = ?1 ORDER BY timestamp",
+        )?;
+        let rows = stmt.query_map(params![account_name], snapshot_from_row)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read quota history")
+    }
+
+    pub fn rename_account(&self, old_name: &str, new_name: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE snapshots SET account_name = ?2 WHERE account_name = ?1",
+                params![old_name, new_name],
+            )
+            .context("Failed to rename quota history")?;
+        self.conn
+            .execute(
+                "UPDATE quota_cache SET account_name = ?2 WHERE account_name = ?1",
+                params![old_name, new_name],
+            )
+            .context("Failed to rename cached quota")?;
+        Ok(())
+    }
+
+    pub fn remove_account(&self, account_name: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM snapshots WHERE account_name = ?1",
+                params![account_name],
+            )
+            .context("Failed to remove quota history")?;
+        self.conn
+            .execute(
+                "DELETE FROM quota_cache WHERE account_name = ?1",
+                params![account_name],
+            )
+            .context("Failed to remove cached quota")?;
+        Ok(())
+    }
+
+    /// Replace the cached quota of an account
+    pub fn cache_quota(&self, account_name: &str, quota: &QuotaInfo) -> Result<()> {
+        let json = serde_json::to_string(quota).context("Failed to serialize quota")?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO quota_cache (account_name, fetched_at, quota)
+                 VALUES (?1, ?2, ?3)",
+                params![account_name, quota.last_updated.timestamp_millis(), json],
+            )
+            .context("Failed to cache quota")?;
+        Ok(())
+    }
+
+    pub fn cached_quotas(&self) -> Result<Vec<(String, CachedQuota)>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT account_name, fetched_at, quota FROM quota_cache")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut cached = Vec::new();
+        for row in rows {
+            let (account_name, millis, json) = row.context("Failed to read cached quotas")?;
+            // Entries written by another version may not parse, they are simply refetched
+            let Ok(quota) = serde_json::from_str(&json) else {
+                continue;
+            };
+            cached.push((
+                account_name,
+                CachedQuota {
+                    fetched_at: chrono::DateTime::from_timestamp_millis(millis).unwrap_or_default(),
+                    quota,
+                },
+            ));
+        }
+        Ok(cached)
+    }
+
+    /// Downsample on first use and then once a day, old snapshots don't pile up any faster
+    pub fn downsample_if_due(
+        &mut self,
+        config: &HistoryConfig,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<usize> {
+        if self
+            .downsampled_at
+            .is_some_and(|at| now - at < chrono::Duration::days(1))
+        {
+            return Ok(0);
+        }
+        self.downsampled_at = Some(now);
+        self.downsample(config, now)
+    }
+
+    /// Reduce snapshots older than `config.raw_days` to the last one per hour or day.
+    /// Usage values are running totals, so the last snapshot of a bucket is its rollup.
+    pub fn downsample(
+        &self,
+        config: &HistoryConfig,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<usize> {
+        let bucket_millis: i64 = match config.retention {
+            Retention::All => return Ok(0),
+            Retention::Hourly => 60 * 60 * 1000,
+            Retention::Daily => 24 * 60 * 60 * 1000,
+        };
+        let cutoff = (now - chrono::Duration::days(config.raw_days as i64)).timestamp_millis();
+
+        // SQLite returns the other columns from the row that holds MAX(timestamp)
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM snapshots WHERE timestamp < ?1 AND rowid NOT IN (
+                    SELECT rowid FROM (
+                        SELECT rowid, MAX(timestamp) FROM snapshots WHERE timestamp < ?1
+                        GROUP BY account_name, timestamp / ?2
+                    )
+                )",
+                params![cutoff, bucket_millis],
+            )
+            .context("Failed to downsample quota history")?;
+
+        Ok(removed)
+    }
+}
+
+fn insert(conn: &Connection, account_name: &str, snapshot: &QuotaSnapshot) -> Result<usize> {
+    conn.execute(
+        "INSERT OR IGNORE INTO snapshots (account_name, timestamp, tokens_used, requests_made, cost)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            account_name,
+            snapshot.timestamp.timestamp_millis(),
+            snapshot.tokens_used.map(|v| v as i64),
+            snapshot.requests_made.map(|v| v as i64),
+            snapshot.cost,
+        ],
+    )
+    .context("Failed to store quota snapshot")
+}
+
+fn snapshot_from_row(row: &rusqlite::Row) -> rusqlite::Result<QuotaSnapshot> {
+    let millis: i64 = row.get(0)?;
+    Ok(QuotaSnapshot {
+        timestamp: chrono::DateTime::from_timestamp_millis(millis).unwrap_or_default(),
+        tokens_used: row.get::<_, Option<i64>>(1)?.map(|v| v as u64),
+        requests_made: row.get::<_, Option<i64>>(2)?.map(|v| v as u64),
+        cost: row.get(3)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn db_with(account_name: &str, timestamps: &[&str]) -> HistoryDb {
+        let db = HistoryDb::open(Path::new(":memory:")).unwrap();
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            let snapshot = QuotaSnapshot {
+                timestamp: at(timestamp),
+                tokens_used: Some(i as u64),
+                requests_made: None,
+                cost: None,
+            };
+            db.insert(account_name, &snapshot).unwrap();
+        }
+        db
+    }
+
+    fn timestamps(db: &HistoryDb, account_name: &str) -> Vec<chrono::DateTime<chrono::Utc>> {
+        db.snapshots(account_name)
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.timestamp)
+            .collect()
+    }
+
+    fn config(retention: Retention) -> HistoryConfig {
+        HistoryConfig {
+            retention,
+            raw_days: 7,
+        }
+    }
+
+    #[test]
+    fn downsample_keeps_last_snapshot_per_hour() {
+        let db = db_with(
+            "work",
+            &[
+                "2026-03-01T10:05:00Z",
+                "2026-03-01T10:40:00Z",
+                "2026-03-01T11:10:00Z",
+                "2026-03-01T11:20:00Z",
+                "2026-03-01T11:50:00Z",
+            ],
+        );
+
+        let removed = db
+            .downsample(&config(Retention::Hourly), at("2026-03-20T00:00:00Z"))
+            .unwrap();
+
+        assert_eq!(removed, 3);
+        assert_eq!(
+            timestamps(&db, "work"),
+            [at("2026-03-01T10:40:00Z"), at("2026-03-01T11:50:00Z")]
+        );
+        // The rollup keeps the running total of the last snapshot in the bucket
+        assert_eq!(
+            db.last_snapshot("work").unwrap().unwrap().tokens_used,
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn downsample_keeps_recent_snapshots_and_other_accounts_apart() {
+        let db = db_with(
+            "work",
+            &[
+                "2026-03-01T08:00:00Z",
+                "2026-03-01T20:00:00Z",
+                "2026-03-19T08:00:00Z",
+                "2026-03-19T09:00:00Z",
+            ],
+        );
+        db.insert(
+            "home",
+            &QuotaSnapshot {
+                timestamp: at("2026-03-01T09:00:00Z"),
+                tokens_used: Some(1),
+                requests_made: None,
+                cost: None,
+            },
+        )
+        .unwrap();
+
+        db.downsample(&config(Retention::Daily), at("2026-03-20T00:00:00Z"))
+            .unwrap();
+
+        assert_eq!(
+            timestamps(&db, "work"),
+            [
+                at("2026-03-01T20:00:00Z"),
+                at("2026-03-19T08:00:00Z"),
+                at("2026-03-19T09:00:00Z")
+            ]
+        );
+        assert_eq!(timestamps(&db, "home"), [at("2026-03-01T09:00:00Z")]);
+    }
+
+    #[test]
+    fn downsample_with_retention_all_keeps_everything() {
+        let db = db_with("work", &["2026-01-01T10:05:00Z", "2026-01-01T10:10:00Z"]);
+
+        let removed = db
+            .downsample(&config(Retention::All), at("2026-03-20T00:00:00Z"))
+            .unwrap();
+
+        assert_eq!(removed, 0);
+        assert_eq!(timestamps(&db, "work").len(), 2);
+    }
+
+    #[test]
+    fn downsample_if_due_runs_at_most_once_a_day() {
+        let mut db = db_with("work", &["2026-03-01T10:05:00Z", "2026-03-01T10:40:00Z"]);
+        let config = config(Retention::Hourly);
+        let now = at("2026-03-20T00:00:00Z");
+
+        assert_eq!(db.downsample_if_due(&config, now).unwrap(), 1);
+
+        let snapshot = QuotaSnapshot {
+            timestamp: at("2026-03-01T10:50:00Z"),
+            tokens_used: Some(9),
+            requests_made: None,
+            cost: None,
+        };
+        db.insert("work", &snapshot).unwrap();
+        let later = now + chrono::Duration::hours(23);
+        assert_eq!(db.downsample_if_due(&config, later).unwrap(), 0);
+        let next_day = now + chrono::Duration::days(1);
+        assert_eq!(db.downsample_if_due(&config, next_day).unwrap(), 1);
+        assert_eq!(timestamps(&db, "work"), [at("2026-03-01T10:50:00Z")]);
+    }
+}
@@ -1,8 +1,14 @@
+mod credentials;
+mod history;
+
+pub use credentials::StoreKind;
+
 use anyhow::{bail, Context, Result};
-use keyring::Entry;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
@@ -12,6 +18,52 @@ pub struct Account {
     pub email: Option<String>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub last_updated: chrono::DateTime<chrono::Utc>,
+    /// Limits set by the user, merged over whatever the provider reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    /// API base URL used instead of the provider's default, e.g. a gateway mirroring its API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Set for accounts from `config.toml`, whose credentials are read on every fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_from: Option<crate::config::CredentialSource>,
+}
+
+/// Limits set by the user, compared against the usage the provider reports for its own
+/// billing period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    #[serde(flatten)]
+    pub limits: crate::providers::TokenLimits,
+}
+
+impl Budget {
+    pub fn is_empty(&self) -> bool {
+        self.limits.max_tokens.is_none()
+            && self.limits.max_requests.is_none()
+            && self.limits.max_cost.is_none()
+    }
+
+    /// Short description such as "5.0M tokens, $50.00"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(max_tokens) = self.limits.max_tokens {
+            parts.push(format!(
+                "{} tokens",
+                crate::format::format_number(max_tokens)
+            ));
+        }
+        if let Some(max_requests) = self.limits.max_requests {
+            parts.push(format!(
+                "{} requests",
+                crate::format::format_number(max_requests)
+            ));
+        }
+        if let Some(max_cost) = self.limits.max_cost {
+            parts.push(format!("${:.2}", max_cost));
+        }
+        parts.join(", ")
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,21 +91,27 @@ impl QuotaSnapshot {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QuotaHistory {
-    pub account_name: String,
-    pub snapshots: Vec<QuotaSnapshot>,
+/// Last quota fetched for an account and when it was fetched
+#[derive(Debug, Clone)]
+pub struct CachedQuota {
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub quota: crate::providers::QuotaInfo,
 }
 
-const MAX_HISTORY_SIZE: usize = 100;
-
 #[derive(Debug, Serialize, Deserialize)]
 struct AccountsIndex {
     accounts: Vec<Account>,
 }
 
+#[derive(Clone)]
 pub struct SecureStorage {
     config_dir: PathBuf,
+    history_config: crate::config::HistoryConfig,
+    credentials: credentials::CredentialStore,
+    /// Accounts from `config.toml`, listed after the ones added with `tokstat login`
+    config_accounts: Vec<Account>,
+    /// History database, opened on first use and shared by all clones
+    history: Arc<Mutex<Option<history::HistoryDb>>>,
 }
 
 impl SecureStorage {
@@ -61,17 +119,112 @@ impl SecureStorage {
         let config_dir = dirs::config_dir()
             .context("Failed to get config directory")?
             .join("tokstat");
+        Self::in_dir(config_dir)
+    }
 
+    /// Storage kept in `config_dir` instead of the user's config directory
+    pub(crate) fn in_dir(config_dir: PathBuf) -> Result<Self> {
         fs::create_dir_all(&config_dir).context("Failed to create config directory")?;
 
-        Ok(Self { config_dir })
+        Ok(Self {
+            credentials: credentials::CredentialStore::new(&config_dir),
+            config_dir,
+            history_config: Default::default(),
+            config_accounts: Vec::new(),
+            history: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Use the retention settings from `config.toml` when storing snapshots
+    pub fn set_history_config(&mut self, history_config: crate::config::HistoryConfig) {
+        self.history_config = history_config;
+    }
+
+    /// Use the credential backend from `config.toml`
+    pub fn set_credentials_config(&mut self, config: &crate::config::CredentialsConfig) {
+        self.credentials.configure(config);
+    }
+
+    /// List the `[[accounts]]` from `config.toml` along with the stored accounts
+    pub fn set_config_accounts(&mut self, accounts: &[crate::config::AccountConfig]) {
+        // Warned about once here, `list_accounts` skips them quietly
+        if let Ok(index) = self.load_index() {
+            for account in accounts {
+                if index
+                    .accounts
+                    .iter()
+                    .any(|stored| stored.name == account.name)
+                {
+                    tracing::warn!(
+                        "Ignoring account '{}' from config.toml, an account with that name was added with `tokstat login`",
+                        account.name
+                    );
+                }
+            }
+        }
+
+        let now = chrono::Utc::now();
+        self.config_accounts = accounts
+            .iter()
+            .map(|account| Account {
+                name: account.name.clone(),
+                provider: account.provider.clone(),
+                email: None,
+                created_at: now,
+                last_updated: now,
+                budget: account.budget.clone(),
+                base_url: account.base_url.clone(),
+                credentials_from: Some(account.credentials_from.clone()),
+            })
+            .collect();
+    }
+
+    /// Error for an account that isn't in the index, pointing at `config.toml` if it's there
+    fn missing_account(&self, name: &str) -> anyhow::Error {
+        if self
+            .config_accounts
+            .iter()
+            .any(|account| account.name == name)
+        {
+            anyhow::anyhow!(
+                "Account '{}' is defined in {}, change it there",
+                name,
+                self.config_path().display()
+            )
+        } else {
+            anyhow::anyhow!("Account '{}' not found", name)
+        }
+    }
+
+    /// Encrypted file used when credentials aren't kept in the keyring
+    pub fn vault_path(&self) -> &std::path::Path {
+        self.credentials.vault_path()
     }
 
     fn index_path(&self) -> PathBuf {
         self.config_dir.join("accounts.json")
     }
 
-    fn history_path(&self) -> PathBuf {
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    /// Unix socket `tokstat daemon` serves its latest results on
+    pub fn daemon_socket_path(&self) -> PathBuf {
+        self.config_dir.join("daemon.sock")
+    }
+
+    /// Alerts already sent, so they aren't repeated on every refresh
+    pub fn alert_state_path(&self) -> PathBuf {
+        self.config_dir.join("alert_state.json")
+    }
+
+    fn history_db_path(&self) -> PathBuf {
+        self.config_dir.join("history.db")
+    }
+
+    /// Quota history file used before the SQLite store
+    fn legacy_history_path(&self) -> PathBuf {
         self.config_dir.join("quota_history.json")
     }
 
@@ -101,28 +254,49 @@ impl SecureStorage {
         Ok(())
     }
 
-    fn load_history(&self) -> Result<Vec<QuotaHistory>> {
-        let path = self.history_path();
-
-        if !path.exists() {
-            return Ok(Vec::new());
+    /// Run `f` on the history database, opening it and importing `quota_history.json` on
+    /// first use, and downsampling old snapshots at most once a day
+    fn with_history<T>(&self, f: impl FnOnce(&history::HistoryDb) -> Result<T>) -> Result<T> {
+        let mut guard = self
+            .history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let db = match &mut *guard {
+            Some(db) => db,
+            None => guard.insert(self.open_history_db()?),
+        };
+        if let Err(e) = db.downsample_if_due(&self.history_config, chrono::Utc::now()) {
+            tracing::warn!("Failed to downsample quota history: {:#}", e);
         }
-
-        let content = fs::read_to_string(&path).context("Failed to read quota history")?;
-
-        let history: Vec<QuotaHistory> =
-            serde_json::from_str(&content).context("Failed to parse quota history")?;
-
-        Ok(history)
+        f(db)
     }
 
-    fn save_history(&self, history: &[QuotaHistory]) -> Result<()> {
-        let content =
-            serde_json::to_string_pretty(history).context("Failed to serialize quota history")?;
-
-        fs::write(self.history_path(), content).context("Failed to write quota history")?;
+    fn open_history_db(&self) -> Result<history::HistoryDb> {
+        let mut db = history::HistoryDb::open(&self.history_db_path())?;
+
+        // Another tokstat process may be importing the same file; the import skips rows that
+        // are already there, and whoever renames the file last finds it gone
+        let legacy_path = self.legacy_history_path();
+        if legacy_path.exists() {
+            match db.import_json(&legacy_path) {
+                Ok(imported) => tracing::info!(
+                    "Imported {} snapshots from {}",
+                    imported,
+                    legacy_path.display()
+                ),
+                Err(_) if !legacy_path.exists() => {}
+                Err(e) => return Err(e),
+            }
+            match fs::rename(&legacy_path, legacy_path.with_extension("json.migrated")) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(e).context("Failed to move the old quota history file aside");
+                }
+                _ => {}
+            }
+        }
 
-        Ok(())
+        Ok(db)
     }
 
     pub fn add_quota_snapshot(
@@ -130,78 +304,90 @@ impl SecureStorage {
         account_name: &str,
         quota: &crate::providers::QuotaInfo,
     ) -> Result<bool> {
-        let mut history = self.load_history()?;
         let new_snapshot = QuotaSnapshot::from_quota_info(quota);
 
-        let account_history = history.iter_mut().find(|h| h.account_name == account_name);
-
-        let changed = match account_history {
-            Some(h) => {
-                let changed = h
-                    .snapshots
-                    .last()
-                    .map(|last| new_snapshot.has_changed_from(last))
-                    .unwrap_or(true);
-
-                if changed {
-                    h.snapshots.push(new_snapshot);
-                    // Keep only the last MAX_HISTORY_SIZE snapshots
-                    if h.snapshots.len() > MAX_HISTORY_SIZE {
-                        h.snapshots.remove(0);
-                    }
-                }
-                changed
-            }
-            None => {
-                // No history for this account yet, create new
-                history.push(QuotaHistory {
-                    account_name: account_name.to_string(),
-                    snapshots: vec![new_snapshot],
-                });
-                true
-            }
-        };
+        self.with_history(|db| {
+            let changed = db
+                .last_snapshot(account_name)?
+                .map(|last| new_snapshot.has_changed_from(&last))
+                .unwrap_or(true);
 
-        if changed {
-            self.save_history(&history)?;
-        }
+            if changed {
+                db.insert(account_name, &new_snapshot)?;
+            }
 
-        Ok(changed)
+            Ok(changed)
+        })
     }
 
     pub fn get_quota_history(&self, account_name: &str) -> Result<Vec<QuotaSnapshot>> {
-        let history = self.load_history()?;
+        self.with_history(|db| db.snapshots(account_name))
+    }
 
-        Ok(history
+    /// Remember the latest quota of an account for `--max-age` and `--offline`
+    pub fn cache_quota(&self, quota: &crate::providers::QuotaInfo) -> Result<()> {
+        self.with_history(|db| db.cache_quota(&quota.account_name, quota))
+    }
+
+    /// Last fetched quota of every account that has been fetched before
+    pub fn cached_quotas(&self) -> Result<HashMap<String, CachedQuota>> {
+        Ok(self
+            .with_history(|db| db.cached_quotas())?
             .into_iter()
-            .find(|h| h.account_name == account_name)
-            .map(|h| h.snapshots)
-            .unwrap_or_default())
+            .collect())
     }
 
     pub fn list_accounts(&self) -> Result<Vec<Account>> {
-        let index = self.load_index()?;
-        Ok(index.accounts)
+        let mut accounts = self.load_index()?.accounts;
+
+        for account in &self.config_accounts {
+            if !accounts.iter().any(|stored| stored.name == account.name) {
+                accounts.push(account.clone());
+            }
+        }
+
+        Ok(accounts)
     }
 
     pub fn get_account(&self, name: &str) -> Result<Account> {
-        let index = self.load_index()?;
-
-        index
-            .accounts
+        self.list_accounts()?
             .into_iter()
             .find(|acc| acc.name == name)
             .context(format!("Account '{}' not found", name))
     }
 
-    pub fn save_account(&self, account: Account) -> Result<()> {
+    /// Add an account, or replace the stored one with the same name in its place in the list
+    pub fn save_account(&self, mut account: Account) -> Result<()> {
         let mut index = self.load_index()?;
 
-        // Remove existing account with same name
-        index.accounts.retain(|acc| acc.name != account.name);
+        match index
+            .accounts
+            .iter_mut()
+            .find(|existing| existing.name == account.name)
+        {
+            Some(existing) => {
+                // Logging in again replaces the key, not the budget set for the account
+                if account.budget.is_none() && existing.provider == account.provider {
+                    account.budget = existing.budget.take();
+                }
+                *existing = account;
+            }
+            None => index.accounts.push(account),
+        }
+
+        self.save_index(&index)
+    }
+
+    /// Replace the stored account with the same name, keeping its position in the list
+    pub fn update_account(&self, account: Account) -> Result<()> {
+        let mut index = self.load_index()?;
 
-        // Add new account
-        index.accounts.push(account);
+        let target = index
+            .accounts
+            .iter_mut()
+            .find(|existing| existing.name == account.name)
+            .ok_or_else(|| self.missing_account(&account.name))?;
+        *target = account;
 
         self.save_index(&index)
     }
@@ -213,48 +399,72 @@ impl SecureStorage {
         index.accounts.retain(|acc| acc.name != name);
 
         if index.accounts.len() == len_before {
-            anyhow::bail!("Account '{}' not found", name);
+            return Err(self.missing_account(name));
         }
 
-        // Also remove credentials from keyring
+        // Also remove credentials from the keyring or vault
         self.delete_credentials(name)?;
 
         // Remove quota history for this account
-        let mut history = self.load_history()?;
-        history.retain(|h| h.account_name != name);
-        let _ = self.save_history(&history);
+        let _ = self.with_history(|db| db.remove_account(name));
 
         self.save_index(&index)
     }
 
-    pub fn store_credentials(&self, account_name: &str, credentials: &str) -> Result<()> {
-        let entry =
-            Entry::new("tokstat", account_name).context("Failed to create keyring entry")?;
-
-        entry
-            .set_password(credentials)
-            .context("Failed to store credentials in keyring")?;
-
-        Ok(())
+    /// Store an account's credentials, asking for a vault passphrase only if `interactive`
+    pub fn store_credentials(
+        &self,
+        account_name: &str,
+        credentials: &str,
+        interactive: bool,
+    ) -> Result<()> {
+        self.credentials.set(account_name, credentials, interactive)
     }
 
     pub fn get_credentials(&self, account_name: &str) -> Result<String> {
-        let entry =
-            Entry::new("tokstat", account_name).context("Failed to create keyring entry")?;
-
-        entry
-            .get_password()
-            .context("Failed to retrieve credentials from keyring")
+        self.credentials.get(account_name)
     }
 
     pub fn delete_credentials(&self, account_name: &str) -> Result<()> {
-        let entry =
-            Entry::new("tokstat", account_name).context("Failed to create keyring entry")?;
+        self.credentials.delete(account_name)
+    }
 
-        // Ignore error if entry doesn't exist
-        let _ = entry.delete_password();
+    /// Ask for the vault passphrase up front if the vault is going to be read
+    pub fn unlock_credentials(&self) -> Result<()> {
+        self.credentials.unlock_if_needed()
+    }
 
-        Ok(())
+    /// Where new credentials are stored with the current `credentials.backend`
+    pub fn default_credential_store(&self) -> StoreKind {
+        self.credentials.default_store()
+    }
+
+    /// Ask for the vault passphrase if there is a vault, whatever the configured backend
+    pub fn unlock_vault(&self) -> Result<()> {
+        self.credentials.unlock_vault()
+    }
+
+    /// Move an account's credentials from one backend to the other.
+    ///
+    /// Returns `false` if they were already only in `to`, so migrating twice is harmless.
+    pub fn migrate_credentials(
+        &self,
+        account_name: &str,
+        from: StoreKind,
+        to: StoreKind,
+    ) -> Result<bool> {
+        let secret = match self.credentials.get_from(from, account_name) {
+            Ok(secret) => secret,
+            Err(e) => {
+                if self.credentials.get_from(to, account_name).is_ok() {
+                    return Ok(false);
+                }
+                return Err(e);
+            }
+        };
+        self.credentials.set_in(to, account_name, &secret)?;
+        self.credentials.delete_from(from, account_name)?;
+        Ok(true)
     }
 
     pub fn rename_account(&self, old_name: &str, new_name: &str) -> Result<()> {
@@ -276,18 +486,15 @@ impl SecureStorage {
             .accounts
             .iter_mut()
             .find(|account| account.name == old_name)
-            .context(format!("Account '{}' not found", old_name))?;
+            .ok_or_else(|| self.missing_account(old_name))?;
 
         let credentials = self.get_credentials(old_name)?;
-        self.store_credentials(new_name, &credentials)?;
+        // Reading the old credentials already unlocked the vault
+        self.store_credentials(new_name, &credentials, false)?;
         self.delete_credentials(old_name)?;
 
         // Rename quota history
-        let mut history = self.load_history()?;
-        if let Some(h) = history.iter_mut().find(|h| h.account_name == old_name) {
-            h.account_name = new_name.to_string();
-            self.save_history(&history)?;
-        }
+        self.with_history(|db| db.rename_account(old_name, new_name))?;
 
         target.name = new_name.to_string();
         target.last_updated = chrono::Utc::now();
@@ -295,14 +502,64 @@ impl SecureStorage {
         self.save_index(&index)
     }
 
+    /// Set or clear the manual budget of an account
+    pub fn set_budget(&self, name: &str, budget: Option<Budget>) -> Result<()> {
+        let mut index = self.load_index()?;
+
+        let target = index
+            .accounts
+            .iter_mut()
+            .find(|account| account.name == name)
+            .ok_or_else(|| self.missing_account(name))?;
+
+        target.budget = budget.filter(|budget| !budget.is_empty());
+        target.last_updated = chrono::Utc::now();
+
+        self.save_index(&index)
+    }
+
     pub fn save_accounts_order(&self, accounts: &[Account]) -> Result<()> {
+        // Accounts from config.toml keep the order of the file
         let index = AccountsIndex {
-            accounts: accounts.to_vec(),
+            accounts: accounts
+                .iter()
+                .filter(|account| account.credentials_from.is_none())
+                .cloned()
+                .collect(),
         };
         self.save_index(&index)
     }
 }
 
+/// Lock `<path>.lock` exclusively until the returned file is dropped, so read-modify-write
+/// cycles on `path` don't overwrite each other across tokstat processes
+pub fn lock_file(path: &Path) -> Result<fs::File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+/// Replace `path` by writing next to it and renaming, so readers never see half a file
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    fs::write(&tmp_path, content)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
 // Add missing dirs dependency
 mod dirs {
     use std::env;
@@ -320,3 +577,124 @@ mod dirs {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{AccountConfig, CredentialSource};
+
+    fn config_account(name: &str) -> AccountConfig {
+        AccountConfig {
+            name: name.to_string(),
+            provider: "openrouter".to_string(),
+            credentials_from: CredentialSource::Env("OPENROUTER_API_KEY".to_string()),
+            base_url: None,
+            budget: None,
+        }
+    }
+
+    fn stored_account(name: &str) -> Account {
+        Account {
+            name: name.to_string(),
+            provider: "openrouter".to_string(),
+            email: None,
+            created_at: chrono::Utc::now(),
+            last_updated: chrono::Utc::now(),
+            budget: None,
+            base_url: None,
+            credentials_from: None,
+        }
+    }
+
+    #[test]
+    fn config_accounts_are_listed_after_stored_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = SecureStorage::in_dir(dir.path().to_path_buf()).unwrap();
+        storage.save_account(stored_account("work")).unwrap();
+        storage.set_config_accounts(&[config_account("ci"), config_account("nightly")]);
+
+        let names: Vec<String> = storage
+            .list_accounts()
+            .unwrap()
+            .into_iter()
+            .map(|account| account.name)
+            .collect();
+        assert_eq!(names, ["work", "ci", "nightly"]);
+        assert!(storage
+            .get_account("ci")
+            .unwrap()
+            .credentials_from
+            .is_some());
+    }
+
+    #[test]
+    fn stored_accounts_shadow_config_accounts_with_the_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = SecureStorage::in_dir(dir.path().to_path_buf()).unwrap();
+        storage.save_account(stored_account("work")).unwrap();
+        storage.set_config_accounts(&[config_account("work"), config_account("ci")]);
+
+        let accounts = storage.list_accounts().unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].name, "work");
+        assert!(accounts[0].credentials_from.is_none());
+        assert_eq!(accounts[1].name, "ci");
+    }
+
+    #[test]
+    fn saving_an_existing_account_keeps_its_place_and_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = SecureStorage::in_dir(dir.path().to_path_buf()).unwrap();
+        let mut work = stored_account("work");
+        work.budget = Some(Budget {
+            limits: crate::providers::TokenLimits {
+                max_cost: Some(50.0),
+                ..Default::default()
+            },
+        });
+        storage.save_account(work).unwrap();
+        storage.save_account(stored_account("home")).unwrap();
+
+        storage.save_account(stored_account("work")).unwrap();
+
+        let accounts = storage.list_accounts().unwrap();
+        assert_eq!(accounts[0].name, "work");
+        assert_eq!(
+            accounts[0].budget.as_ref().unwrap().limits.max_cost,
+            Some(50.0)
+        );
+        assert_eq!(accounts[1].name, "home");
+    }
+
+    #[test]
+    fn history_is_downsampled_when_first_opened() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = SecureStorage::in_dir(dir.path().to_path_buf()).unwrap();
+        storage.set_history_config(crate::config::HistoryConfig {
+            retention: crate::config::Retention::Daily,
+            raw_days: 7,
+        });
+        // Midday, so the snapshots share a day
+        let old = (chrono::Utc::now() - chrono::Duration::days(30))
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc();
+        let db = history::HistoryDb::open(&storage.history_db_path()).unwrap();
+        for minutes in [0, 10, 20] {
+            let snapshot = QuotaSnapshot {
+                timestamp: old + chrono::Duration::minutes(minutes),
+                tokens_used: Some(minutes as u64),
+                requests_made: None,
+                cost: None,
+            };
+            db.insert("work", &snapshot).unwrap();
+        }
+        drop(db);
+
+        let history = storage.get_quota_history("work").unwrap();
+
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].tokens_used, Some(20));
+    }
+}
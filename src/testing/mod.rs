@@ -0,0 +1,110 @@
+//! Helpers shared by the test modules
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::config::HttpConfig;
+use crate::http::HttpClient;
+
+/// Minimal HTTP server answering each path with a canned status and JSON body.
+/// A route like `/report?page=2` only answers requests with that query parameter.
+pub struct Stub {
+    pub base_url: String,
+    /// Every request received, including its body
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Stub {
+    pub async fn start(routes: &[(&str, u16, &str)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let routes: Vec<(String, u16, String)> = routes
+            .iter()
+            .map(|(path, status, body)| (path.to_string(), *status, body.to_string()))
+            .collect();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                let header_end = loop {
+                    if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break buf.len(),
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                };
+                let content_length = String::from_utf8_lossy(&buf[..header_end])
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    match stream.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&buf).to_string();
+                let target = request.split_whitespace().nth(1).unwrap_or_default();
+                let (path, query) = target.split_once('?').unwrap_or((target, ""));
+                // `/path?name=value` routes only answer requests carrying that parameter, and
+                // take precedence over the plain path
+                let with_query = routes.iter().find(|(route, _, _)| {
+                    route
+                        .split_once('?')
+                        .is_some_and(|(route_path, parameter)| {
+                            route_path == path && query.split('&').any(|p| p == parameter)
+                        })
+                });
+                let (status, body) = with_query
+                    .or_else(|| routes.iter().find(|(route, _, _)| route == path))
+                    .map(|(_, status, body)| (*status, body.clone()))
+                    .unwrap_or((404, "{}".to_string()));
+                received.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+pub fn client() -> HttpClient {
+    HttpClient::new(&HttpConfig {
+        retries: 0,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Body of a request captured by the stub
+pub fn body(request: &str) -> serde_json::Value {
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    serde_json::from_str(body).unwrap()
+}
+
+/// Environment variables are process-wide, so tests changing them take turns
+static ENV: Mutex<()> = Mutex::new(());
+
+pub fn lock_env() -> MutexGuard<'static, ()> {
+    ENV.lock().unwrap_or_else(|e| e.into_inner())
+}
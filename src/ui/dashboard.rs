@@ -12,19 +12,29 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Wrap},
     Frame, Terminal,
 };
+use std::collections::{HashMap, HashSet};
 use std::io;
+use tokio::sync::mpsc;
 use tokio::time::Duration;
 
-use crate::providers::QuotaInfo;
-use crate::storage::{Account, QuotaSnapshot, SecureStorage};
-
-const PROVIDERS: &[(&str, &str)] = &[
-    ("azure", "Azure OpenAI"),
-    ("copilot", "GitHub Copilot"),
-    ("openrouter", "OpenRouter"),
-];
-
-pub async fn run(storage: SecureStorage, accounts: Vec<Account>) -> Result<()> {
+use super::history_chart::{self, HistoryView};
+use crate::alerts::{Alert, Alerter};
+use crate::auth::LoginOptions;
+use crate::config::{Config, DisplayConfig, UsageLevel};
+use crate::forecast;
+use crate::format::format_number;
+use crate::http::HttpClient;
+use crate::providers::registry::{ProviderInfo, PROVIDERS};
+use crate::providers::{FetchOptions, QuotaInfo};
+use crate::storage::{Account, Budget, QuotaSnapshot, SecureStorage};
+
+pub async fn run(
+    storage: SecureStorage,
+    accounts: Vec<Account>,
+    fetch_options: FetchOptions,
+    client: HttpClient,
+    config: Config,
+) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -33,7 +43,7 @@ pub async fn run(storage: SecureStorage, accounts: Vec<Account>) -> Result<()> {
     let mut terminal = Terminal::new(backend)?;
     terminal.hide_cursor()?;
 
-    let mut app = App::new(storage, accounts).await?;
+    let mut app = App::new(storage, accounts, fetch_options, client, config);
     let res = run_app(&mut terminal, &mut app).await;
 
     // Restore terminal
@@ -61,79 +71,299 @@ enum Mode {
         selected_provider: usize,
     },
     CreatingAccountName {
-        provider_id: String,
-        provider_name: String,
+        provider: &'static ProviderInfo,
         buffer: String,
     },
     Deleting,
+    EditingBudget(BudgetForm),
+}
+
+/// Input state of the budget editor
+struct BudgetForm {
+    /// Max tokens, max requests and max cost as typed; empty means no limit
+    fields: [String; 3],
+    /// Focused field
+    focus: usize,
+}
+
+const BUDGET_FIELD_LABELS: [&str; 3] = ["Max tokens", "Max requests", "Max cost ($)"];
+
+impl BudgetForm {
+    fn new(budget: Option<&Budget>) -> Self {
+        let budget = budget.cloned().unwrap_or_default();
+        Self {
+            fields: [
+                budget
+                    .limits
+                    .max_tokens
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                budget
+                    .limits
+                    .max_requests
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                budget
+                    .limits
+                    .max_cost
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+            ],
+            focus: 0,
+        }
+    }
+
+    /// Parse the form, `None` means the account has no budget
+    fn to_budget(&self) -> Result<Option<Budget>> {
+        fn parse<T: std::str::FromStr>(label: &str, value: &str) -> Result<Option<T>> {
+            let value = value.trim();
+            if value.is_empty() {
+                return Ok(None);
+            }
+            value
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("{} must be a number", label))
+        }
+
+        let budget = Budget {
+            limits: crate::providers::TokenLimits {
+                max_tokens: parse(BUDGET_FIELD_LABELS[0], &self.fields[0])?,
+                max_requests: parse(BUDGET_FIELD_LABELS[1], &self.fields[1])?,
+                max_cost: parse(BUDGET_FIELD_LABELS[2], &self.fields[2])?,
+            },
+        };
+        if budget.limits.max_cost.is_some_and(|cost: f64| cost <= 0.0) {
+            anyhow::bail!("Max cost must be greater than zero");
+        }
+
+        Ok(Some(budget).filter(|budget| !budget.is_empty()))
+    }
+}
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// A refresh running in the background, streaming one result per account
+struct Refresh {
+    rx: mpsc::UnboundedReceiver<(usize, Result<QuotaInfo>)>,
+    /// Quotas fetched before this are from the cache or the daemon
+    started: chrono::DateTime<chrono::Utc>,
+    /// Account names in the order they were handed to the fetcher
+    account_names: Vec<String>,
+    pending: HashSet<String>,
+    failed: usize,
+}
+
+/// Outcome of the most recent quota fetch for one account
+enum QuotaState {
+    /// No fetch has finished for this account yet
+    Pending,
+    Loaded(QuotaInfo),
+    Failed(String),
+}
+
+/// An account together with everything the dashboard knows about its quota
+struct AccountEntry {
+    account: Account,
+    state: QuotaState,
+    /// When a fetch for this account last succeeded
+    last_success: Option<chrono::DateTime<chrono::Local>>,
+    /// Whether the loaded quota came from the cache or the daemon instead of the provider
+    cached: bool,
+    history: Vec<QuotaSnapshot>,
+}
+
+impl AccountEntry {
+    fn new(account: Account) -> Self {
+        Self {
+            account,
+            state: QuotaState::Pending,
+            last_success: None,
+            cached: false,
+            history: Vec::new(),
+        }
+    }
 }
 
 struct App {
     storage: SecureStorage,
-    accounts: Vec<Account>,
-    quotas: Vec<QuotaInfo>,
-    quota_histories: Vec<Vec<QuotaSnapshot>>,
+    fetch_options: FetchOptions,
+    client: HttpClient,
+    config: Config,
+    alerter: Alerter,
+    accounts: Vec<AccountEntry>,
+    refresh: Option<Refresh>,
+    spinner_frame: usize,
     selected_index: usize,
+    history_view: HistoryView,
     should_quit: bool,
     status_message: String,
     mode: Mode,
 }
 
 impl App {
-    async fn new(storage: SecureStorage, accounts: Vec<Account>) -> Result<Self> {
+    fn new(
+        storage: SecureStorage,
+        accounts: Vec<Account>,
+        fetch_options: FetchOptions,
+        client: HttpClient,
+        config: Config,
+    ) -> Self {
+        let alerter = Alerter::new(&storage, &config, &client);
         let mut app = Self {
             storage,
-            accounts,
-            quotas: Vec::new(),
-            quota_histories: Vec::new(),
+            fetch_options,
+            client,
+            config,
+            alerter,
+            accounts: accounts.into_iter().map(AccountEntry::new).collect(),
+            refresh: None,
+            spinner_frame: 0,
             selected_index: 0,
+            history_view: HistoryView::default(),
             should_quit: false,
             status_message: "Loading...".to_string(),
             mode: Mode::Viewing,
         };
 
-        app.refresh_quotas().await;
+        app.start_refresh();
+
+        app
+    }
+
+    fn selected(&self) -> Option<&AccountEntry> {
+        self.accounts.get(self.selected_index)
+    }
+
+    fn account_list(&self) -> Vec<Account> {
+        self.accounts
+            .iter()
+            .map(|entry| entry.account.clone())
+            .collect()
+    }
+
+    /// Replace the account list, keeping quota state for accounts that still exist
+    fn set_accounts(&mut self, accounts: Vec<Account>) {
+        let mut previous: HashMap<String, AccountEntry> = self
+            .accounts
+            .drain(..)
+            .map(|entry| (entry.account.name.clone(), entry))
+            .collect();
 
-        Ok(app)
+        self.accounts = accounts
+            .into_iter()
+            .map(|account| match previous.remove(&account.name) {
+                Some(entry) => AccountEntry { account, ..entry },
+                None => AccountEntry::new(account),
+            })
+            .collect();
     }
 
-    async fn refresh_quotas(&mut self) {
+    /// Kick off a background refresh of all accounts, replacing any refresh in progress
+    fn start_refresh(&mut self) {
         self.status_message = "Refreshing quota information...".to_string();
-        self.quotas.clear();
-        self.quota_histories.clear();
-        let mut has_error = false;
-
-        for account in &self.accounts {
-            match crate::providers::fetch_quota(account).await {
-                Ok(mut quota) => {
-                    quota.account_name = account.name.clone();
-
-                    // Store snapshot and check if it changed
-                    let _changed = self.storage.add_quota_snapshot(&account.name, &quota);
-
-                    // Load history for this account
-                    match self.storage.get_quota_history(&account.name) {
-                        Ok(history) => {
-                            self.quota_histories.push(history);
+
+        let accounts = self.account_list();
+        let account_names: Vec<String> = accounts.iter().map(|a| a.name.clone()).collect();
+        let rx = crate::providers::spawn_quota_fetches(
+            self.storage.clone(),
+            accounts,
+            self.fetch_options,
+            self.client.clone(),
+        );
+
+        self.refresh = Some(Refresh {
+            rx,
+            started: chrono::Utc::now(),
+            pending: account_names.iter().cloned().collect(),
+            account_names,
+            failed: 0,
+        });
+    }
+
+    fn is_refreshing(&self, account_name: &str) -> bool {
+        self.refresh
+            .as_ref()
+            .is_some_and(|refresh| refresh.pending.contains(account_name))
+    }
+
+    /// Apply every result that has arrived since the last call without blocking
+    fn poll_refresh(&mut self) {
+        let Some(refresh) = self.refresh.as_mut() else {
+            return;
+        };
+
+        let mut finished = false;
+        let mut received = false;
+        let mut alerts: Vec<Alert> = Vec::new();
+        loop {
+            match refresh.rx.try_recv() {
+                Ok((index, result)) => {
+                    let Some(account_name) = refresh.account_names.get(index).cloned() else {
+                        continue;
+                    };
+                    refresh.pending.remove(&account_name);
+
+                    // The account may have been deleted or renamed while its fetch was running
+                    let Some(entry) = self
+                        .accounts
+                        .iter_mut()
+                        .find(|entry| entry.account.name == account_name)
+                    else {
+                        continue;
+                    };
+
+                    received = true;
+                    alerts.extend(self.alerter.observe([(&entry.account, &result)]));
+
+                    match result {
+                        Ok(quota) => {
+                            // Fresh results were recorded by the fetch, load the updated history
+                            entry.history = self
+                                .storage
+                                .get_quota_history(&account_name)
+                                .unwrap_or_default();
+                            entry.cached = quota.last_updated < refresh.started;
+                            entry.last_success =
+                                Some(quota.last_updated.with_timezone(&chrono::Local));
+                            entry.state = QuotaState::Loaded(quota);
                         }
-                        Err(_) => {
-                            self.quota_histories.push(Vec::new());
+                        Err(e) => {
+                            entry.state = QuotaState::Failed(e.to_string());
+                            refresh.failed += 1;
                         }
                     }
-
-                    self.quotas.push(quota);
                 }
-                Err(e) => {
-                    self.status_message = format!("Error fetching {}: {}", account.name, e);
-                    has_error = true;
-                    self.quota_histories.push(Vec::new());
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
                 }
             }
         }
 
-        if !has_error {
-            self.status_message =
-                format!("Last updated: {}", chrono::Local::now().format("%H:%M:%S"));
+        if received {
+            self.alerter.notify(&alerts);
+        }
+
+        let in_dialog = !matches!(self.mode, Mode::Viewing);
+        if finished {
+            if !in_dialog {
+                let updated = chrono::Local::now().format("%H:%M:%S");
+                self.status_message = match refresh.failed {
+                    0 => format!("Last updated: {}", updated),
+                    1 => format!("Last updated: {} (1 account failed)", updated),
+                    n => format!("Last updated: {} ({} accounts failed)", updated, n),
+                };
+            }
+            self.refresh = None;
+        } else if !in_dialog {
+            let done = refresh.account_names.len() - refresh.pending.len();
+            self.status_message = format!(
+                "Refreshing quota information... ({}/{})",
+                done,
+                refresh.account_names.len()
+            );
         }
     }
 
@@ -160,13 +390,8 @@ impl App {
         self.accounts
             .swap(self.selected_index, self.selected_index - 1);
         self.selected_index -= 1;
-        // Also swap quotas and histories to keep them in sync
-        self.quotas
-            .swap(self.selected_index, self.selected_index + 1);
-        self.quota_histories
-            .swap(self.selected_index, self.selected_index + 1);
         // Save the new order
-        let _ = self.storage.save_accounts_order(&self.accounts);
+        let _ = self.storage.save_accounts_order(&self.account_list());
     }
 
     fn move_account_down(&mut self) {
@@ -176,22 +401,19 @@ impl App {
         self.accounts
             .swap(self.selected_index, self.selected_index + 1);
         self.selected_index += 1;
-        // Also swap quotas and histories to keep them in sync
-        self.quotas
-            .swap(self.selected_index, self.selected_index - 1);
-        self.quota_histories
-            .swap(self.selected_index, self.selected_index - 1);
         // Save the new order
-        let _ = self.storage.save_accounts_order(&self.accounts);
+        let _ = self.storage.save_accounts_order(&self.account_list());
     }
 }
 
+// Key handling predates these lints, keep it as written
+#[allow(clippy::collapsible_match, clippy::needless_borrow)]
 async fn run_app(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     app: &mut App,
 ) -> Result<()> {
     let mut last_refresh = std::time::Instant::now();
-    let refresh_duration = std::time::Duration::from_secs(60);
+    let refresh_duration = std::time::Duration::from_secs(app.config.dashboard.refresh_interval);
 
     loop {
         terminal.draw(|f| ui(f, app))?;
@@ -206,13 +428,13 @@ async fn run_app(
                                 app.should_quit = true;
                             }
                             KeyCode::Char('R') => {
-                                app.refresh_quotas().await;
+                                app.start_refresh();
                                 last_refresh = std::time::Instant::now();
                             }
                             KeyCode::Char('r') => {
-                                if let Some(account) = app.accounts.get(app.selected_index) {
+                                if let Some(entry) = app.selected() {
                                     app.mode = Mode::Renaming {
-                                        buffer: account.name.clone(),
+                                        buffer: entry.account.name.clone(),
                                     };
                                     app.status_message =
                                         "Renaming mode: press Enter to confirm, Esc to cancel"
@@ -225,13 +447,29 @@ async fn run_app(
                                 };
                                 app.status_message = "Select provider: ↑↓ to navigate, Enter to select, Esc to cancel".to_string();
                             }
+                            KeyCode::Char('w') => {
+                                app.history_view.window = app.history_view.window.next();
+                            }
+                            KeyCode::Char('m') => {
+                                app.history_view.metric = app.history_view.metric.next();
+                            }
+                            KeyCode::Char('b') => {
+                                if let Some(entry) = app.selected() {
+                                    app.mode = Mode::EditingBudget(BudgetForm::new(
+                                        entry.account.budget.as_ref(),
+                                    ));
+                                    app.status_message =
+                                        "Budget: Tab to switch fields, Enter to save, Esc to cancel"
+                                            .to_string();
+                                }
+                            }
                             KeyCode::Char('d') => {
-                                if let Some(account) = app.accounts.get(app.selected_index) {
-                                    app.mode = Mode::Deleting;
+                                if let Some(entry) = app.selected() {
                                     app.status_message = format!(
                                         "Delete account '{}'? Press Enter to confirm, Esc to cancel",
-                                        account.name
+                                        entry.account.name
                                     );
+                                    app.mode = Mode::Deleting;
                                 }
                             }
                             KeyCode::Down | KeyCode::Char('j') => {
@@ -246,27 +484,78 @@ async fn run_app(
                             KeyCode::Char('K') => {
                                 app.move_account_up();
                             }
+                            KeyCode::Char('a') => {
+                                let Some(account) = app.selected().map(|e| e.account.clone())
+                                else {
+                                    continue;
+                                };
+                                if account.provider != "copilot"
+                                    || account.credentials_from.is_some()
+                                {
+                                    app.status_message = format!(
+                                        "'{}' has no device flow to rerun, only Copilot logins do",
+                                        account.name
+                                    );
+                                    continue;
+                                }
+
+                                // Exit terminal UI temporarily to run the device flow
+                                disable_raw_mode()?;
+                                execute!(
+                                    terminal.backend_mut(),
+                                    LeaveAlternateScreen,
+                                    DisableMouseCapture
+                                )?;
+                                terminal.show_cursor()?;
+
+                                let result = crate::auth::copilot::reauth(
+                                    &app.storage,
+                                    &app.client,
+                                    &account,
+                                )
+                                .await;
+
+                                // Restore terminal UI
+                                enable_raw_mode()?;
+                                execute!(
+                                    terminal.backend_mut(),
+                                    EnterAlternateScreen,
+                                    EnableMouseCapture
+                                )?;
+                                terminal.clear()?;
+
+                                match result.and_then(|()| app.storage.list_accounts()) {
+                                    Ok(accounts) => {
+                                        app.set_accounts(accounts);
+                                        app.start_refresh();
+                                        last_refresh = std::time::Instant::now();
+                                        app.status_message =
+                                            format!("✓ Re-authenticated '{}'", account.name);
+                                    }
+                                    Err(e) => {
+                                        app.status_message =
+                                            format!("Re-authentication failed: {}", e);
+                                    }
+                                }
+                            }
                             _ => {}
                         }
                     }
                     Mode::Renaming { buffer } => match key.code {
                         KeyCode::Enter => {
-                            if let Some(account) = app.accounts.get(app.selected_index) {
+                            if let Some(entry) = app.accounts.get_mut(app.selected_index) {
                                 let trimmed = buffer.trim();
                                 if trimmed.is_empty() {
                                     app.status_message = "Name cannot be empty".to_string();
                                 } else if let Err(err) =
-                                    app.storage.rename_account(&account.name, trimmed)
+                                    app.storage.rename_account(&entry.account.name, trimmed)
                                 {
                                     app.status_message = format!("Rename failed: {}", err);
                                 } else {
-                                    if let Some(target) = app.accounts.get_mut(app.selected_index) {
-                                        target.name = trimmed.to_string();
-                                    }
+                                    // Quota state and history stay with the entry
+                                    entry.account.name = trimmed.to_string();
                                     app.status_message = "Account renamed".to_string();
                                     app.mode = Mode::Viewing;
-                                    app.refresh_quotas().await;
-                                    last_refresh = std::time::Instant::now();
                                 }
                             }
                         }
@@ -286,13 +575,12 @@ async fn run_app(
                     },
                     Mode::CreatingAccount { selected_provider } => match key.code {
                         KeyCode::Enter => {
-                            let (provider_id, provider_name) = PROVIDERS[*selected_provider];
+                            let provider = &PROVIDERS[*selected_provider];
                             // Generate default account name as initial buffer
                             let default_name =
-                                format!("{}_{}", provider_id, chrono::Utc::now().timestamp());
+                                format!("{}_{}", provider.id, chrono::Utc::now().timestamp());
                             app.mode = Mode::CreatingAccountName {
-                                provider_id: provider_id.to_string(),
-                                provider_name: provider_name.to_string(),
+                                provider,
                                 buffer: default_name,
                             };
                             app.status_message =
@@ -315,22 +603,18 @@ async fn run_app(
                         }
                         _ => {}
                     },
-                    Mode::CreatingAccountName {
-                        provider_id,
-                        provider_name,
-                        buffer,
-                    } => match key.code {
+                    Mode::CreatingAccountName { provider, buffer } => match key.code {
                         KeyCode::Enter => {
                             let account_name = if buffer.trim().is_empty() {
-                                format!("{}_{}", provider_id, chrono::Utc::now().timestamp())
+                                format!("{}_{}", provider.id, chrono::Utc::now().timestamp())
                             } else {
                                 buffer.trim().to_string()
                             };
-                            // Clone the values before changing mode
-                            let provider_id = provider_id.clone();
-                            let provider_name = provider_name.clone();
+                            // Copy the provider out before changing mode
+                            let provider: &'static ProviderInfo = provider;
                             app.mode = Mode::Viewing;
-                            app.status_message = format!("Creating {} account...", provider_name);
+                            app.status_message =
+                                format!("Creating {} account...", provider.display_name);
 
                             // Exit terminal UI temporarily to run login flow
                             disable_raw_mode()?;
@@ -342,19 +626,13 @@ async fn run_app(
                             terminal.show_cursor()?;
 
                             // Run the appropriate login flow
-                            let result = match provider_id.as_str() {
-                                "azure" => {
-                                    crate::auth::azure::login(&app.storage, &account_name).await
-                                }
-                                "copilot" => {
-                                    crate::auth::copilot::login(&app.storage, &account_name).await
-                                }
-                                "openrouter" => {
-                                    crate::auth::openrouter::login(&app.storage, &account_name)
-                                        .await
-                                }
-                                _ => unreachable!(),
-                            };
+                            let result = (provider.login)(
+                                &app.storage,
+                                &app.client,
+                                &account_name,
+                                &LoginOptions::default(),
+                            )
+                            .await;
 
                             // Restore terminal UI
                             enable_raw_mode()?;
@@ -371,15 +649,15 @@ async fn run_app(
                                     // Reload accounts
                                     match app.storage.list_accounts() {
                                         Ok(accounts) => {
-                                            app.accounts = accounts;
+                                            app.set_accounts(accounts);
                                             if !app.accounts.is_empty() {
                                                 app.selected_index = app.accounts.len() - 1;
                                             }
                                             app.status_message = format!(
                                                 "✓ {} account '{}' added successfully",
-                                                provider_name, account_name
+                                                provider.display_name, account_name
                                             );
-                                            app.refresh_quotas().await;
+                                            app.start_refresh();
                                             last_refresh = std::time::Instant::now();
                                         }
                                         Err(e) => {
@@ -407,27 +685,68 @@ async fn run_app(
                         }
                         _ => {}
                     },
+                    Mode::EditingBudget(form) => match key.code {
+                        KeyCode::Enter => match form.to_budget() {
+                            Ok(budget) => {
+                                if let Some(entry) = app.accounts.get_mut(app.selected_index) {
+                                    match app
+                                        .storage
+                                        .set_budget(&entry.account.name, budget.clone())
+                                    {
+                                        Ok(()) => {
+                                            entry.account.budget = budget;
+                                            app.mode = Mode::Viewing;
+                                            // Refetch so the new limits replace the merged ones
+                                            app.start_refresh();
+                                            last_refresh = std::time::Instant::now();
+                                            app.status_message = "Budget saved".to_string();
+                                        }
+                                        Err(e) => {
+                                            app.status_message =
+                                                format!("Failed to save budget: {}", e);
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                app.status_message = e.to_string();
+                            }
+                        },
+                        KeyCode::Esc => {
+                            app.mode = Mode::Viewing;
+                            app.status_message = "Budget editing cancelled".to_string();
+                        }
+                        KeyCode::Tab | KeyCode::Down => {
+                            form.focus = (form.focus + 1) % form.fields.len();
+                        }
+                        KeyCode::BackTab | KeyCode::Up => {
+                            form.focus = (form.focus + form.fields.len() - 1) % form.fields.len();
+                        }
+                        KeyCode::Backspace => {
+                            form.fields[form.focus].pop();
+                        }
+                        KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
+                            form.fields[form.focus].push(c);
+                        }
+                        _ => {}
+                    },
                     Mode::Deleting => match key.code {
                         KeyCode::Enter => {
-                            if let Some(account) = app.accounts.get(app.selected_index) {
-                                let account_name = account.name.clone();
+                            if let Some(entry) = app.selected() {
+                                let account_name = entry.account.name.clone();
                                 match app.storage.remove_account(&account_name) {
                                     Ok(()) => {
                                         // Reload accounts
                                         match app.storage.list_accounts() {
                                             Ok(accounts) => {
-                                                app.accounts = accounts;
+                                                app.set_accounts(accounts);
                                                 if app.accounts.is_empty() {
                                                     app.selected_index = 0;
                                                 } else if app.selected_index >= app.accounts.len() {
                                                     app.selected_index = app.accounts.len() - 1;
                                                 }
-                                                app.quotas.clear();
-                                                app.quota_histories.clear();
                                                 app.status_message =
                                                     format!("Account '{}' deleted", account_name);
-                                                app.refresh_quotas().await;
-                                                last_refresh = std::time::Instant::now();
                                             }
                                             Err(e) => {
                                                 app.status_message =
@@ -453,9 +772,13 @@ async fn run_app(
             }
         }
 
-        // Auto-refresh every 60 seconds
-        if last_refresh.elapsed() >= refresh_duration {
-            app.refresh_quotas().await;
+        // Apply any results that arrived from the background refresh
+        app.poll_refresh();
+        app.spinner_frame = app.spinner_frame.wrapping_add(1);
+
+        // Auto-refresh on the configured interval, unless the previous refresh is still running
+        if last_refresh.elapsed() >= refresh_duration && app.refresh.is_none() {
+            app.start_refresh();
             last_refresh = std::time::Instant::now();
         }
 
@@ -558,6 +881,20 @@ fn ui(f: &mut Frame, app: &App) {
                     .add_modifier(Modifier::BOLD),
             ),
             Span::raw(" to delete, "),
+            Span::styled(
+                "b",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" for budget, "),
+            Span::styled(
+                "a",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(" to re-authenticate, "),
             Span::styled(
                 "↑↓",
                 Style::default()
@@ -597,9 +934,8 @@ fn ui(f: &mut Frame, app: &App) {
             Line::from(vec![
                 Span::styled("Current: ", Style::default().fg(Color::Gray)),
                 Span::raw(
-                    app.accounts
-                        .get(app.selected_index)
-                        .map(|a| a.name.as_str())
+                    app.selected()
+                        .map(|entry| entry.account.name.as_str())
                         .unwrap_or(""),
                 ),
             ]),
@@ -661,6 +997,10 @@ fn ui(f: &mut Frame, app: &App) {
         // The custom cursor (▌) is already rendered in the text above
     }
 
+    if let Mode::EditingBudget(form) = &app.mode {
+        render_budget_editor(f, app, form);
+    }
+
     if let Mode::CreatingAccount { selected_provider } = &app.mode {
         let area = centered_rect(50, 40, f.size());
         f.render_widget(Clear, area);
@@ -668,7 +1008,7 @@ fn ui(f: &mut Frame, app: &App) {
         let items: Vec<ListItem> = PROVIDERS
             .iter()
             .enumerate()
-            .map(|(i, (id, name))| {
+            .map(|(i, provider)| {
                 let style = if i == *selected_provider {
                     Style::default()
                         .fg(Color::Black)
@@ -677,7 +1017,10 @@ fn ui(f: &mut Frame, app: &App) {
                 } else {
                     Style::default()
                 };
-                let content = format!("{} - {}", id, name);
+                let content = format!(
+                    "{} {} - {}",
+                    provider.icon, provider.id, provider.display_name
+                );
                 ListItem::new(content).style(style)
             })
             .collect();
@@ -692,12 +1035,7 @@ fn ui(f: &mut Frame, app: &App) {
         f.render_widget(list, area);
     }
 
-    if let Mode::CreatingAccountName {
-        provider_name,
-        buffer,
-        ..
-    } = &app.mode
-    {
+    if let Mode::CreatingAccountName { provider, buffer } = &app.mode {
         let area = centered_rect(60, 30, f.size());
         f.render_widget(Clear, area);
 
@@ -705,7 +1043,7 @@ fn ui(f: &mut Frame, app: &App) {
             Line::from(vec![
                 Span::styled("📝 Create ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::styled(
-                    provider_name,
+                    provider.display_name,
                     Style::default()
                         .add_modifier(Modifier::BOLD)
                         .fg(Color::LightMagenta),
@@ -790,9 +1128,8 @@ fn ui(f: &mut Frame, app: &App) {
         f.render_widget(Clear, area);
 
         let account_name = app
-            .accounts
-            .get(app.selected_index)
-            .map(|a| a.name.clone())
+            .selected()
+            .map(|entry| entry.account.name.clone())
             .unwrap_or_default();
 
         let prompt = Paragraph::new(vec![
@@ -870,7 +1207,8 @@ fn render_account_list(f: &mut Frame, app: &App, area: Rect) {
         .accounts
         .iter()
         .enumerate()
-        .map(|(i, account)| {
+        .map(|(i, entry)| {
+            let account = &entry.account;
             let style = if i == app.selected_index {
                 Style::default()
                     .fg(Color::Black)
@@ -880,8 +1218,40 @@ fn render_account_list(f: &mut Frame, app: &App, area: Rect) {
                 Style::default()
             };
 
-            let content = format!("{} ({})", account.name, account.provider);
-            ListItem::new(content).style(style)
+            let mut spans = vec![Span::raw(format!(
+                "{} ({})",
+                account.name, account.provider
+            ))];
+            if app.is_refreshing(&account.name) {
+                let frame = SPINNER_FRAMES[app.spinner_frame % SPINNER_FRAMES.len()];
+                spans.push(Span::styled(
+                    format!(" {} refreshing…", frame),
+                    if i == app.selected_index {
+                        Style::default().fg(Color::Black)
+                    } else {
+                        Style::default().fg(Color::DarkGray)
+                    },
+                ));
+            } else if let QuotaState::Failed(_) = entry.state {
+                spans.push(Span::styled(
+                    " ✗",
+                    if i == app.selected_index {
+                        Style::default().fg(Color::Black)
+                    } else {
+                        Style::default().fg(Color::Red)
+                    },
+                ));
+            } else if entry.cached {
+                spans.push(Span::styled(
+                    " ⏳",
+                    if i == app.selected_index {
+                        Style::default().fg(Color::Black)
+                    } else {
+                        Style::default().fg(Color::Yellow)
+                    },
+                ));
+            }
+            ListItem::new(Line::from(spans)).style(style)
         })
         .collect();
 
@@ -893,7 +1263,7 @@ fn render_account_list(f: &mut Frame, app: &App, area: Rect) {
 fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
     if app.accounts.is_empty() {
         // Show getting started guide when no accounts configured
-        let guide_text = vec![
+        let mut guide_text = vec![
             Line::from(""),
             Line::from(Span::styled(
                 "Getting Started",
@@ -911,24 +1281,18 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
                 "Supported providers:",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+        ];
+        guide_text.extend(PROVIDERS.iter().map(|provider| {
             Line::from(vec![
                 Span::styled("  • ", Style::default().fg(Color::Magenta)),
-                Span::styled("Azure OpenAI", Style::default()),
+                Span::styled(provider.display_name, Style::default()),
                 Span::styled(
-                    " - Azure-hosted OpenAI models",
+                    format!(" - {}", provider.description),
                     Style::default().fg(Color::Gray),
                 ),
-            ]),
-            Line::from(vec![
-                Span::styled("  • ", Style::default().fg(Color::Magenta)),
-                Span::styled("GitHub Copilot", Style::default()),
-                Span::styled(" - AI coding assistant", Style::default().fg(Color::Gray)),
-            ]),
-            Line::from(vec![
-                Span::styled("  • ", Style::default().fg(Color::Magenta)),
-                Span::styled("OpenRouter", Style::default()),
-                Span::styled(" - LLM API aggregator", Style::default().fg(Color::Gray)),
-            ]),
+            ])
+        }));
+        guide_text.extend([
             Line::from(""),
             Line::from(vec![
                 Span::styled("Press ", Style::default().fg(Color::Gray)),
@@ -943,7 +1307,7 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
                     Style::default().fg(Color::Gray),
                 ),
             ]),
-        ];
+        ]);
 
         let guide = Paragraph::new(guide_text).alignment(Alignment::Left).block(
             Block::default()
@@ -954,36 +1318,43 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
         return;
     }
 
-    if app.quotas.is_empty() {
-        // Show error status if available, otherwise show generic message
-        let message_text = if app.status_message.starts_with("Error") {
-            format!("Failed to fetch quota data\n\n{}", app.status_message)
-        } else {
-            "No quota data available".to_string()
-        };
-
-        let message = Paragraph::new(message_text)
-            .alignment(Alignment::Center)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Quota Details"),
-            );
-        f.render_widget(message, area);
-        return;
-    }
-
-    if app.selected_index >= app.quotas.len() {
+    let Some(entry) = app.selected() else {
         return;
-    }
+    };
+    let account = &entry.account;
+
+    let quota = match &entry.state {
+        QuotaState::Loaded(quota) => quota,
+        QuotaState::Failed(error) => {
+            render_fetch_error(
+                f,
+                entry,
+                error,
+                app.is_refreshing(&account.name),
+                &app.config.display,
+                area,
+            );
+            return;
+        }
+        QuotaState::Pending => {
+            let message_text = if app.is_refreshing(&account.name) {
+                "Fetching quota data..."
+            } else {
+                "No quota data available"
+            };
 
-    let quota = &app.quotas[app.selected_index];
-    let account = &app.accounts[app.selected_index];
-    let history = if app.selected_index < app.quota_histories.len() {
-        &app.quota_histories[app.selected_index]
-    } else {
-        &[] as &[QuotaSnapshot]
+            let message = Paragraph::new(message_text)
+                .alignment(Alignment::Center)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Quota Details"),
+                );
+            f.render_widget(message, area);
+            return;
+        }
     };
+    let history = entry.history.as_slice();
 
     // Determine which gauges to display
     let has_requests = quota.usage.requests_made.is_some();
@@ -994,29 +1365,8 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
         .filter(|&&x| x)
         .count();
 
-    // Calculate total height needed for info (7) + gauges (3 each) + model panel (dynamic)
-    let info_height = 7u16;
-    let gauges_height = (gauge_count as u16) * 3u16;
-
-    let model_panel_lines = build_model_multipliers_lines(account);
-    let model_panel_height = (model_panel_lines.len() as u16).saturating_add(2).max(4);
-
-    // Build constraints: info panel + gauges (fixed height) + history (fills remaining) + model panel (bottom)
-    let constraints: Vec<Constraint> = vec![
-        Constraint::Length(info_height),
-        Constraint::Length(gauges_height),
-        Constraint::Min(0),
-        Constraint::Length(model_panel_height),
-    ];
-
-    // Split the area
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(constraints)
-        .split(area);
-
-    // Account info (first chunk)
-    let info_text = vec![
+    // Account info
+    let mut info_text = vec![
         Line::from(vec![
             Span::styled("Provider: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(&account.provider),
@@ -1024,13 +1374,32 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
         Line::from(vec![
             Span::styled("Account: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(&account.name),
+            Span::styled(
+                account
+                    .email
+                    .as_ref()
+                    .map(|email| format!(" ({})", email))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::Gray),
+            ),
         ]),
         Line::from(vec![
             Span::styled(
                 "Last Updated: ",
                 Style::default().add_modifier(Modifier::BOLD),
             ),
-            Span::raw(quota.last_updated.format("%Y-%m-%d %H:%M:%S").to_string()),
+            Span::raw(app.config.display.format_datetime(quota.last_updated)),
+            Span::styled(
+                if entry.cached {
+                    format!(
+                        " (cached {} ago)",
+                        forecast::format_duration(chrono::Utc::now() - quota.last_updated)
+                    )
+                } else {
+                    String::new()
+                },
+                Style::default().fg(Color::Yellow),
+            ),
         ]),
         Line::from(vec![
             Span::styled(
@@ -1040,12 +1409,52 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
             Span::raw(
                 quota
                     .reset_date
-                    .map(|dt| dt.format("%Y-%m-%d").to_string())
+                    .map(|dt| app.config.display.format_datetime(dt))
                     .unwrap_or_else(|| "Unknown".to_string()),
             ),
         ]),
     ];
+    if let Some(budget) = &account.budget {
+        info_text.push(Line::from(vec![
+            Span::styled("Budget: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(budget.summary()),
+        ]));
+    }
+    let now = chrono::Utc::now();
+    for forecast in forecast::forecast(history, quota, &app.config.forecast, now) {
+        let style = if forecast.exhausts_before_reset {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        info_text.push(Line::from(vec![
+            Span::styled("Forecast: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(forecast.describe(now), style),
+        ]));
+    }
+
+    // Calculate total height needed for info (lines + borders) + gauges (3 each) + model panel (dynamic)
+    let info_height = (info_text.len() as u16).saturating_add(2);
+    let gauges_height = (gauge_count as u16) * 3u16;
+
+    let model_panel_lines = build_model_multipliers_lines(account);
+    let model_panel_height = (model_panel_lines.len() as u16).saturating_add(2).max(4);
+
+    // Build constraints: info panel + gauges (fixed height) + history (fills remaining) + model panel (bottom)
+    let constraints: Vec<Constraint> = vec![
+        Constraint::Length(info_height),
+        Constraint::Length(gauges_height),
+        Constraint::Min(0),
+        Constraint::Length(model_panel_height),
+    ];
+
+    // Split the area
+    let main_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
 
+    // Account info (first chunk)
     let info = Paragraph::new(info_text)
         .block(Block::default().borders(Borders::ALL).title("Account Info"))
         .wrap(Wrap { trim: true });
@@ -1075,13 +1484,7 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
                     format_number(max_requests),
                     format_number(remaining)
                 );
-                let col = if r < 0.5 {
-                    Color::Green
-                } else if r < 0.8 {
-                    Color::Yellow
-                } else {
-                    Color::Red
-                };
+                let col = usage_color(&app.config.display, r);
                 (r.min(1.0), lbl, col)
             } else {
                 (
@@ -1115,13 +1518,7 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
                     format_number(max_tokens),
                     r * 100.0
                 );
-                let col = if r < 0.5 {
-                    Color::Green
-                } else if r < 0.8 {
-                    Color::Yellow
-                } else {
-                    Color::Red
-                };
+                let col = usage_color(&app.config.display, r);
                 (r.min(1.0), lbl, col)
             } else {
                 (
@@ -1150,13 +1547,7 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
             if let Some(max_cost) = quota.limits.as_ref().and_then(|l| l.max_cost) {
                 let r = cost / max_cost;
                 let lbl = format!("Cost: ${:.2} / ${:.2} ({:.1}%)", cost, max_cost, r * 100.0);
-                let col = if r < 0.5 {
-                    Color::Green
-                } else if r < 0.8 {
-                    Color::Yellow
-                } else {
-                    Color::Red
-                };
+                let col = usage_color(&app.config.display, r);
                 (r.min(1.0), lbl, col)
             } else {
                 (0.0, format!("Cost: ${:.2}", cost), Color::Gray)
@@ -1174,74 +1565,128 @@ fn render_quota_details(f: &mut Frame, app: &App, area: Rect) {
         f.render_widget(gauge, gauge_chunks[gauge_index]);
     }
 
-    // History section (third chunk) - aligned to top under gauges
-    let mut history_lines: Vec<Line> = Vec::new();
-
-    if history.is_empty() {
-        history_lines.push(Line::from(vec![Span::styled(
-            "No quota history yet. History will be recorded when quotas change.",
-            Style::default().fg(Color::Gray),
-        )]));
-    } else {
-        // Show history entries (most recent first, limited to what fits)
-        for snapshot in history.iter().rev().take(30) {
-            let datetime = snapshot.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
-
-            let mut changes = Vec::new();
-            if let Some(tokens) = snapshot.tokens_used {
-                changes.push(format!("{} tokens", format_number(tokens)));
-            }
-            if let Some(requests) = snapshot.requests_made {
-                changes.push(format!("{} requests", format_number(requests)));
-            }
-            if let Some(cost) = snapshot.cost {
-                changes.push(format!("${:.2} cost", cost));
-            }
-
-            let change_text = if changes.is_empty() {
-                "Quota checked (no data)".to_string()
-            } else {
-                changes.join(", ")
-            };
-
-            let line = Line::from(vec![
-                Span::styled(
-                    format!("[{}] ", datetime),
-                    Style::default().fg(Color::Yellow),
-                ),
-                Span::raw(change_text),
-            ]);
-            history_lines.push(line);
-        }
-
-        if history.len() > 30 {
-            history_lines.push(Line::from(Span::styled(
-                format!("... and {} more entries", history.len() - 30),
-                Style::default().fg(Color::Gray),
-            )));
-        }
-    }
+    // History chart (third chunk) - fills the space under the gauges
+    history_chart::render(
+        f,
+        main_chunks[2],
+        history,
+        quota,
+        &app.history_view,
+        &app.config.display,
+    );
 
-    let history_widget = Paragraph::new(history_lines)
+    // Model multipliers panel (bottom chunk)
+    let multipliers = Paragraph::new(model_panel_lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!("Quota History ({} entries)", history.len())),
+                .title("Model Multipliers"),
         )
         .wrap(Wrap { trim: true });
 
-    f.render_widget(history_widget, main_chunks[2]);
+    f.render_widget(multipliers, main_chunks[3]);
+}
+
+/// Gauge color for a usage ratio (0.0-1.0) according to the configured thresholds
+fn usage_color(display: &DisplayConfig, ratio: f64) -> Color {
+    match display.usage_level(ratio * 100.0) {
+        UsageLevel::Normal => Color::Green,
+        UsageLevel::Warning => Color::Yellow,
+        UsageLevel::Critical => Color::Red,
+    }
+}
 
-    // Model multipliers panel (bottom chunk)
-    let multipliers = Paragraph::new(model_panel_lines)
+fn render_fetch_error(
+    f: &mut Frame,
+    entry: &AccountEntry,
+    error: &str,
+    refreshing: bool,
+    display: &DisplayConfig,
+    area: Rect,
+) {
+    let last_success = entry
+        .last_success
+        .map(|dt| display.format_datetime(dt.with_timezone(&chrono::Utc)))
+        .unwrap_or_else(|| "Never".to_string());
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "⚠ Failed to fetch quota data",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("Provider: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(entry.account.provider.as_str()),
+        ]),
+        Line::from(vec![
+            Span::styled("Account: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(entry.account.name.as_str()),
+            Span::styled(
+                entry
+                    .account
+                    .email
+                    .as_ref()
+                    .map(|email| format!(" ({})", email))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::Gray),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled(
+                "Last Successful Fetch: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(last_success),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(error, Style::default().fg(Color::LightRed))),
+        Line::from(""),
+    ];
+
+    lines.push(if refreshing {
+        Line::from(Span::styled(
+            "Retrying...",
+            Style::default().fg(Color::Gray),
+        ))
+    } else {
+        Line::from(vec![
+            Span::styled("Press ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "R",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(" to retry", Style::default().fg(Color::Gray)),
+        ])
+    });
+    if entry.account.provider == "copilot" && entry.account.credentials_from.is_none() {
+        lines.push(Line::from(vec![
+            Span::styled("Press ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                "a",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                " to sign in to GitHub again",
+                Style::default().fg(Color::Gray),
+            ),
+        ]));
+    }
+
+    let panel = Paragraph::new(lines)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Model Multipliers"),
+                .border_style(Style::default().fg(Color::Red))
+                .title("Quota Details"),
         )
         .wrap(Wrap { trim: true });
 
-    f.render_widget(multipliers, main_chunks[3]);
+    f.render_widget(panel, area);
 }
 
 fn build_model_multipliers_lines(account: &Account) -> Vec<Line<'static>> {
@@ -1270,14 +1715,85 @@ fn build_model_multipliers_lines(account: &Account) -> Vec<Line<'static>> {
     ]
 }
 
-fn format_number(n: u64) -> String {
-    if n >= 1_000_000 {
-        format!("{:.1}M", n as f64 / 1_000_000.0)
-    } else if n >= 1_000 {
-        format!("{:.1}K", n as f64 / 1_000.0)
-    } else {
-        n.to_string()
+fn render_budget_editor(f: &mut Frame, app: &App, form: &BudgetForm) {
+    let area = centered_rect(50, 40, f.size());
+    f.render_widget(Clear, area);
+
+    let account_name = app
+        .selected()
+        .map(|entry| entry.account.name.as_str())
+        .unwrap_or("");
+    let focused_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+
+    let mut content = vec![
+        Line::from(Span::styled(
+            format!("💰 Budget for {}", account_name),
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::LightCyan),
+        )),
+        Line::from(Span::styled(
+            "Leave a field empty to use the provider's limit",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(""),
+    ];
+
+    for (i, (label, value)) in BUDGET_FIELD_LABELS.iter().zip(&form.fields).enumerate() {
+        let focused = form.focus == i;
+        let mut spans = vec![
+            Span::styled(
+                format!("{:<14}", label),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            if value.is_empty() {
+                Span::styled("(none)", Style::default().fg(Color::Gray))
+            } else if focused {
+                Span::styled(value.as_str(), focused_style)
+            } else {
+                Span::raw(value.as_str())
+            },
+        ];
+        if focused {
+            spans.push(Span::styled(
+                "▌",
+                Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+        content.push(Line::from(spans));
     }
+
+    content.push(Line::from(""));
+    content.push(Line::from(vec![
+        Span::styled(
+            "Enter",
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" to save  •  "),
+        Span::styled(
+            "Esc",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(" to cancel"),
+    ]));
+
+    let prompt = Paragraph::new(content).alignment(Alignment::Left).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::LightCyan))
+            .title(Span::styled(
+                " Edit Budget ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+            .title_alignment(Alignment::Center),
+    );
+    f.render_widget(prompt, area);
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
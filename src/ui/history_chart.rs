@@ -0,0 +1,277 @@
+use chrono::{DateTime, Months, Utc};
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Style},
+    symbols,
+    text::Span,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::config::DisplayConfig;
+use crate::providers::QuotaInfo;
+use crate::storage::QuotaSnapshot;
+
+/// Time span shown in the history chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryWindow {
+    Day,
+    Week,
+    Month,
+    /// From one month before the reset date up to the reset date
+    BillingPeriod,
+}
+
+impl HistoryWindow {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Day => Self::Week,
+            Self::Week => Self::Month,
+            Self::Month => Self::BillingPeriod,
+            Self::BillingPeriod => Self::Day,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Day => "24h",
+            Self::Week => "7d",
+            Self::Month => "30d",
+            Self::BillingPeriod => "billing period",
+        }
+    }
+
+    /// Start and end of the window; the billing period falls back to 30 days without a reset date
+    fn bounds(
+        self,
+        now: DateTime<Utc>,
+        reset_date: Option<DateTime<Utc>>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        match self {
+            Self::Day => (now - chrono::Duration::hours(24), now),
+            Self::Week => (now - chrono::Duration::days(7), now),
+            Self::Month => (now - chrono::Duration::days(30), now),
+            Self::BillingPeriod => match reset_date {
+                Some(reset) if reset > now => {
+                    let start = reset
+                        .checked_sub_months(Months::new(1))
+                        .unwrap_or(reset - chrono::Duration::days(30));
+                    (start.min(now), reset)
+                }
+                _ => Self::Month.bounds(now, None),
+            },
+        }
+    }
+
+    fn tick_format(self) -> &'static str {
+        match self {
+            Self::Day => "%H:%M",
+            _ => "%m-%d",
+        }
+    }
+}
+
+/// Usage value plotted in the history chart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryMetric {
+    Tokens,
+    Requests,
+    Cost,
+}
+
+impl HistoryMetric {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Tokens => Self::Requests,
+            Self::Requests => Self::Cost,
+            Self::Cost => Self::Tokens,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Requests => "requests",
+            Self::Cost => "cost",
+        }
+    }
+
+    fn snapshot_value(self, snapshot: &QuotaSnapshot) -> Option<f64> {
+        match self {
+            Self::Tokens => snapshot.tokens_used.map(|v| v as f64),
+            Self::Requests => snapshot.requests_made.map(|v| v as f64),
+            Self::Cost => snapshot.cost,
+        }
+    }
+
+    fn limit(self, quota: &QuotaInfo) -> Option<f64> {
+        let limits = quota.limits.as_ref()?;
+        match self {
+            Self::Tokens => limits.max_tokens.map(|v| v as f64),
+            Self::Requests => limits.max_requests.map(|v| v as f64),
+            Self::Cost => limits.max_cost,
+        }
+    }
+
+    fn format_value(self, value: f64) -> String {
+        match self {
+            Self::Cost => format!("${:.2}", value),
+            _ => crate::format::format_number(value.round() as u64),
+        }
+    }
+}
+
+pub struct HistoryView {
+    pub window: HistoryWindow,
+    pub metric: HistoryMetric,
+}
+
+impl Default for HistoryView {
+    fn default() -> Self {
+        Self {
+            window: HistoryWindow::Week,
+            metric: HistoryMetric::Tokens,
+        }
+    }
+}
+
+/// Plot one usage metric over time, with the limit and reset date as reference lines
+pub fn render(
+    f: &mut Frame,
+    area: Rect,
+    history: &[QuotaSnapshot],
+    quota: &QuotaInfo,
+    view: &HistoryView,
+    display: &DisplayConfig,
+) {
+    let now = Utc::now();
+    let (start, end) = view.window.bounds(now, quota.reset_date);
+    let x_of = |t: DateTime<Utc>| (t - start).num_seconds() as f64;
+    let x_max = x_of(end).max(1.0);
+
+    // Snapshots are only stored on change, so the last one before the window still holds at
+    // its start; without it, usage that stayed flat for the whole window wouldn't show
+    let carried = history
+        .iter()
+        .rev()
+        .filter(|snapshot| snapshot.timestamp < start)
+        .find_map(|snapshot| view.metric.snapshot_value(snapshot))
+        .map(|value| (0.0, value));
+    let mut usage: Vec<(f64, f64)> = carried
+        .into_iter()
+        .chain(
+            history
+                .iter()
+                .filter(|snapshot| snapshot.timestamp >= start && snapshot.timestamp <= end)
+                .filter_map(|snapshot| {
+                    view.metric
+                        .snapshot_value(snapshot)
+                        .map(|value| (x_of(snapshot.timestamp), value))
+                }),
+        )
+        .collect();
+    // Likewise extend the line to the current value
+    if let Some(current) = view
+        .metric
+        .snapshot_value(&QuotaSnapshot::from_quota_info(quota))
+    {
+        if !usage.is_empty() {
+            usage.push((x_of(now.min(end)), current));
+        }
+    }
+
+    let title = format!(
+        "Quota History: {}, {} (m: metric, w: window)",
+        view.metric.label(),
+        view.window.label()
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+
+    if usage.is_empty() {
+        let message = Paragraph::new(Span::styled(
+            format!(
+                "No {} history in this window. History is recorded when quotas change.",
+                view.metric.label()
+            ),
+            Style::default().fg(Color::Gray),
+        ))
+        .block(block)
+        .wrap(Wrap { trim: true });
+        f.render_widget(message, area);
+        return;
+    }
+
+    let limit = view.metric.limit(quota);
+    let peak = usage
+        .iter()
+        .map(|(_, value)| *value)
+        .chain(limit)
+        .fold(0.0, f64::max);
+    let y_max = if peak > 0.0 { peak * 1.1 } else { 1.0 };
+
+    let limit_line: Vec<(f64, f64)> = limit
+        .map(|limit| vec![(0.0, limit), (x_max, limit)])
+        .unwrap_or_default();
+    let reset_line: Vec<(f64, f64)> = quota
+        .reset_date
+        .filter(|reset| *reset >= start && *reset <= end)
+        .map(|reset| vec![(x_of(reset), 0.0), (x_of(reset), y_max)])
+        .unwrap_or_default();
+
+    let mut datasets = vec![Dataset::default()
+        .name(view.metric.label())
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Cyan))
+        .data(&usage)];
+    if !limit_line.is_empty() {
+        datasets.push(
+            Dataset::default()
+                .name("limit")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Red))
+                .data(&limit_line),
+        );
+    }
+    if !reset_line.is_empty() {
+        datasets.push(
+            Dataset::default()
+                .name("reset")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Magenta))
+                .data(&reset_line),
+        );
+    }
+
+    let tick_format = view.window.tick_format();
+    let middle = start + (end - start) / 2;
+    let x_labels = [start, middle, end]
+        .into_iter()
+        .map(|t| Span::raw(display.format_datetime_with(t, tick_format)))
+        .collect();
+    let y_labels = [0.0, y_max / 2.0, y_max]
+        .into_iter()
+        .map(|v| Span::raw(view.metric.format_value(v)))
+        .collect();
+
+    let chart = Chart::new(datasets)
+        .block(block)
+        // The history area is short, so allow the legend to take more room than the default
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, x_max])
+                .labels(x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, y_max])
+                .labels(y_labels),
+        );
+
+    f.render_widget(chart, area);
+}
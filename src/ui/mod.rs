@@ -1 +1,2 @@
 pub mod dashboard;
+mod history_chart;